@@ -0,0 +1,94 @@
+//! Compares going through every wall and monster, which is how collision used to work, with
+//! asking the spatial grid. Run with `cargo bench --bench collision`
+use game::logic::TILE;
+use game::spatial::{SpatialGrid, Walls};
+use macroquad::prelude::*;
+use macroquad::rand::{gen_range, srand};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// About the size of Village
+const MAP_WIDTH: f32 = 50. * TILE;
+const MAP_HEIGHT: f32 = 40. * TILE;
+const WALLS: usize = 600;
+const MONSTERS: usize = 200;
+const TICKS: u32 = 300;
+
+fn random_rect(size: f32) -> Rect {
+    let x = gen_range(0., MAP_WIDTH - size);
+    let y = gen_range(0., MAP_HEIGHT - size);
+    Rect::new(x, y, size, size)
+}
+
+fn time(name: &str, run: impl Fn() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut hits = 0;
+    for _ in 0..TICKS {
+        hits += black_box(run());
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{name:<24} {:>8.3} ms per tick ({hits} hits)",
+        elapsed.as_secs_f64() * 1000. / TICKS as f64
+    );
+    elapsed
+}
+
+fn main() {
+    srand(42);
+    let wall_rects: Vec<Rect> = (0..WALLS).map(|_| random_rect(TILE)).collect();
+    let monsters: Vec<Rect> = (0..MONSTERS).map(|_| random_rect(TILE)).collect();
+    let projectiles: Vec<Rect> = (0..MONSTERS / 4).map(|_| random_rect(TILE / 3.)).collect();
+    let walls = Walls::new(wall_rects.clone());
+
+    println!(
+        "{WALLS} walls, {MONSTERS} monsters, {} projectiles",
+        projectiles.len()
+    );
+
+    let naive = time("every wall and monster", || {
+        let wall_hits = monsters
+            .iter()
+            .map(|monster| {
+                wall_rects
+                    .iter()
+                    .filter(|wall| wall.overlaps(monster))
+                    .count()
+            })
+            .sum::<usize>();
+        let monster_hits = projectiles
+            .iter()
+            .map(|proj| {
+                monsters
+                    .iter()
+                    .filter(|monster| monster.overlaps(proj))
+                    .count()
+            })
+            .sum::<usize>();
+        wall_hits + monster_hits
+    });
+
+    let grid = time("spatial grid", || {
+        let wall_hits = monsters
+            .iter()
+            .map(|monster| {
+                walls
+                    .near(*monster)
+                    .filter(|wall| wall.overlaps(monster))
+                    .count()
+            })
+            .sum::<usize>();
+        // Monsters move every tick, so their grid is built again every time
+        let index = SpatialGrid::from_rects(monsters.iter().copied());
+        let monster_hits = projectiles
+            .iter()
+            .map(|proj| {
+                let near = index.query(*proj).into_iter();
+                near.filter(|index| monsters[*index].overlaps(proj)).count()
+            })
+            .sum::<usize>();
+        wall_hits + monster_hits
+    });
+
+    println!("speedup: {:.1}x", naive.as_secs_f64() / grid.as_secs_f64());
+}
@@ -0,0 +1,26 @@
+use game::validate::validate_maps;
+use std::env::{args, set_current_dir};
+use std::process::ExitCode;
+
+// Usage: validate-maps [game directory]
+// The game directory is the one containing assets/, same as where the game is run from
+fn main() -> ExitCode {
+    if let Some(dir) = args().nth(1) {
+        if let Err(err) = set_current_dir(&dir) {
+            eprintln!("Could not open {dir}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let errors = validate_maps();
+    if errors.is_empty() {
+        println!("All maps are valid");
+        return ExitCode::SUCCESS;
+    }
+
+    for err in &errors {
+        println!("{err}");
+    }
+    println!("{} problem(s) found", errors.len());
+    ExitCode::FAILURE
+}
@@ -1,21 +1,37 @@
+use crate::logic::*;
+use crate::map::{Area, Interactable, Monster, RATIO};
+use crate::npc::NPC;
 use crate::player::{Collidable, PlayerState, PIXEL};
+use crate::tileset::{find_tileset, Tile, Tileset};
+use crate::ui::items::ItemEntity;
 use crate::ui::main_menu::MainMenu;
-use crate::{logic::*, map::Area};
 use macroquad::prelude::*;
 use textwrap::Options;
 
+// Anything that gets sorted in the entity layer
+enum Sprite<'a> {
+    Player,
+    Monster(&'a Monster),
+    Npc(&'a NPC),
+    Interactable(&'a Interactable),
+    Item(&'a ItemEntity),
+    TileRow(usize),
+}
+
+// How much of the way to the player the camera moves in a 60th of a second
 const CAM_SPEED: f32 = 1. / 8.;
 
+// Only the UI sheet is drawn with draw_tiles now, map tiles go through their tileset
 const SHEET_SIZE: u16 = 12;
 const BLANK_TILE: u16 = 0;
 pub const TERRAIN_TILE_SIZE: f32 = 16.;
 
 impl Game {
-    pub fn new_camera_offset(&mut self) {
+    pub fn new_camera_offset(&mut self, dt: f32) {
         let mut cam_box = self.cam_box();
 
         // Shift the camera box's center to the player's
-        let player_pos = self.player.hitbox().center();
+        let player_pos = self.player.render_pos();
         let cam_center = cam_box.center();
         cam_box.x += player_pos.x - cam_center.x;
         cam_box.y += player_pos.y - cam_center.y;
@@ -35,25 +51,26 @@ impl Game {
         }
         // So uhm, the camera will start to follow the player once the player has gone out of bound.
         // Since it would be quite nice to hide some easter eggs with it, this is a feauture now.
-        self.set_offset(cam_box.center())
+        self.set_offset(cam_box.center(), dt)
     }
 
-    fn set_offset(&mut self, new_offset: Vec2) {
-        let screen_width = screen_width();
-        let screen_height = screen_height();
+    fn set_offset(&mut self, new_offset: Vec2, dt: f32) {
+        let screen_width = self.input.screen_size.x;
+        let screen_height = self.input.screen_size.y;
 
         let curent_offset = vec2(
             -self.cam_offset.x * screen_width,
             self.cam_offset.y * screen_height,
         );
 
-        // Because S M O O T H
-        self.cam_offset.x += (curent_offset.x - new_offset.x) / screen_width * CAM_SPEED;
-        self.cam_offset.y -= (curent_offset.y - new_offset.y) / screen_height * CAM_SPEED;
+        // Because S M O O T H, and the same amount of smooth at any frame rate
+        let speed = 1. - (1. - CAM_SPEED).powf(dt * 60.);
+        self.cam_offset.x += (curent_offset.x - new_offset.x) / screen_width * speed;
+        self.cam_offset.y -= (curent_offset.y - new_offset.y) / screen_height * speed;
     }
 
     fn bound_box(&self) -> Rect {
-        let mesh = &self.maps[&self.current_map].draw_mesh.terrain;
+        let mesh = self.maps[&self.current_map].draw_mesh.terrain();
         let bound_x = mesh[0].len();
         let bound_y = mesh.len();
         let bounds = vec2(bound_x as f32 * TILE, bound_y as f32 * TILE);
@@ -61,8 +78,8 @@ impl Game {
     }
 
     pub fn cam_box(&self) -> Rect {
-        let screen_width = screen_width();
-        let screen_height = screen_height();
+        let screen_width = self.input.screen_size.x;
+        let screen_height = self.input.screen_size.y;
 
         let cam_pos_x = -self.cam_offset.x * screen_width;
         let cam_pos_y = self.cam_offset.y * screen_height;
@@ -91,14 +108,15 @@ impl Game {
             return;
         }
 
-        self.draw_terrain();
-        self.draw_monsters();
-        self.draw_interactables();
-        self.draw_player();
+        let map = &self.maps[&self.current_map];
+        for (index, layer) in map.draw_mesh.layers.iter().enumerate() {
+            match index == map.draw_mesh.entity_layer {
+                true => self.draw_entity_layer(&layer.tiles),
+                false => self.draw_tile_layer(&layer.tiles),
+            }
+        }
         self.draw_projectiles();
-        self.draw_decorations();
-        self.draw_npcs();
-        self.draw_items();
+        self.draw_overlays();
         self.hud();
 
         match &self.state {
@@ -112,35 +130,46 @@ impl Game {
     fn draw_gui(&self, gui: &GUIType) {
         match gui {
             GUIType::Inventory => self.show_inv(),
+            GUIType::QuestLog => self.show_quest_log(),
+            GUIType::Shop(shop) => self.show_shop(shop),
             GUIType::DeathScreen(death_screen) => {
-                death_screen.draw_buttons(&self.textures["ui"], &self.font)
+                death_screen.draw_buttons(&self.textures["ui"], self.font.as_ref())
             }
-            GUIType::MainMenu(_) => return, // This should not be reachable since it's already been covered
+            GUIType::MainMenu(_) => (), // This should not be reachable since it's already been covered
         }
     }
 
     fn draw_main_menu(&self, menu: &MainMenu) {
         menu.draw_background(&self.textures["menu_bg"], self.cam_box());
-        menu.draw_buttons(&self.textures["ui"], &self.font);
-    }
-
-    fn draw_items(&self) {
-        let items = &self.maps[&self.current_map].items;
-
-        for item in items {
-            item.draw(&self.textures["ui"])
-        }
+        menu.draw_buttons(&self.textures["ui"], self.font.as_ref());
     }
 
-    fn draw_interactables(&self) {
-        let interactables = &self.maps[&self.current_map].interactables;
+    // The "press R" bubbles go over everything, so they can't be hidden behind a tree
+    fn draw_overlays(&self) {
+        let map = &self.maps[&self.current_map];
         let search_box = self.player.search_box();
-        for item in interactables {
-            item.draw(&self.textures["chest"]);
-            if !item.hitbox().overlaps(&search_box) {
-                continue;
+        for interactable in &map.interactables {
+            if interactable.hitbox().overlaps(&search_box) {
+                interactable.draw_overlay(&self.textures["ui"])
+            }
+        }
+        for npc in &map.npcs {
+            if npc.hitbox.overlaps(&search_box) {
+                npc.draw_overlay(&self.textures["ui"]);
+            }
+        }
+        // So it's clear why the way out stopped working
+        if map.is_sealed() {
+            for gate in &map.gates {
+                let rect = gate.hitbox();
+                draw_rectangle(
+                    rect.x,
+                    rect.y,
+                    rect.w,
+                    rect.h,
+                    Color::new(0.6, 0.1, 0.1, 0.5),
+                );
             }
-            item.draw_overlay(&self.textures["ui"])
         }
     }
 
@@ -148,34 +177,69 @@ impl Game {
         let projectiles = &self.maps[&self.current_map].projectiles;
 
         for projectile in projectiles {
-            projectile.draw(&self.textures["player"])
+            projectile.draw(&self.textures)
         }
     }
 
-    fn draw_monsters(&self) {
-        let map = &self.maps[&self.current_map];
-        for monster in map.enemies.iter() {
-            monster.draw(&self.textures);
-        }
-    }
-
-    fn draw_terrain(&self) {
+    fn draw_tile_layer(&self, tiles: &[Vec<Tile>]) {
         let screen = self.cam_box().shift(TILE, TILE);
-        let mesh = &self.maps[&self.current_map].draw_mesh.terrain;
-        let texture = &self.textures["terrain"];
-        draw_tiles(mesh, vec2(0., 0.), texture, Some(screen), TERRAIN_TILE_SIZE);
+        let tilesets = &self.maps[&self.current_map].tilesets;
+        draw_map_tiles(tiles, tilesets, &self.textures, screen, self.anim_clock);
     }
 
-    fn draw_decorations(&self) {
+    // Everything here is drawn from the top of the screen down by where its hitbox ends, so
+    // whatever is lower down ends up in front. The layer's own tiles are sorted by the bottom of
+    // their row
+    fn draw_entity_layer(&self, tiles: &[Vec<Tile>]) {
+        let map = &self.maps[&self.current_map];
+        let mut sprites = vec![(self.player.hitbox().bottom(), Sprite::Player)];
+        for monster in &map.enemies {
+            sprites.push((monster.hitbox().bottom(), Sprite::Monster(monster)));
+        }
+        for npc in &map.npcs {
+            sprites.push((npc.hitbox.bottom(), Sprite::Npc(npc)));
+        }
+        for interactable in &map.interactables {
+            sprites.push((
+                interactable.hitbox().bottom(),
+                Sprite::Interactable(interactable),
+            ));
+        }
+        for item in &map.items {
+            sprites.push((item.hitbox.bottom(), Sprite::Item(item)));
+        }
+        for row in 0..tiles.len() {
+            sprites.push(((row + 1) as f32 * TILE, Sprite::TileRow(row)));
+        }
+        sprites.sort_by(|a, b| a.0.total_cmp(&b.0));
+
         let screen = self.cam_box().shift(TILE, TILE);
-        let mesh = &self.maps[&self.current_map].draw_mesh.decorations;
-        let texture = &self.textures["terrain"];
-        draw_tiles(mesh, vec2(0., 0.), texture, Some(screen), TERRAIN_TILE_SIZE);
+        for (_, sprite) in sprites {
+            match sprite {
+                Sprite::Player => self.draw_player(),
+                Sprite::Monster(monster) => monster.draw(&self.textures),
+                // validate-maps catches missing sprites, a blank NPC beats crashing
+                Sprite::Npc(npc) => match self.textures.get(&npc.sprite) {
+                    Some(texture) => npc.draw(texture),
+                    None => npc.draw(&self.textures["blank"]),
+                },
+                Sprite::Interactable(interactable) => interactable.draw(&self.textures["chest"]),
+                Sprite::Item(item) => item.draw(&self.textures["ui"]),
+                Sprite::TileRow(row) => draw_tile_row(
+                    &tiles[row],
+                    row,
+                    &map.tilesets,
+                    &self.textures,
+                    screen,
+                    self.anim_clock,
+                ),
+            }
+        }
     }
 
     fn draw_player(&self) {
         let player = &self.player;
-        let mouse_pos = self.get_mouse_pos();
+        let mouse_pos = self.input.mouse_pos;
         match self.player.state {
             PlayerState::Attacking(attack) => {
                 player.draw_weapon(&self.textures["ui"]);
@@ -206,39 +270,79 @@ impl Game {
         }
     }
 
-    fn draw_npcs(&self) {
-        let search_box = self.player.search_box();
-        let map = &self.maps[&self.current_map];
-        let npcs = &map.npcs;
-
-        for npc in npcs {
-            npc.draw(&self.textures[&npc.name]);
-
-            if npc.hitbox.overlaps(&search_box) {
-                npc.draw_overlay(&self.textures["ui"]);
-            }
-        }
-    }
-
     fn draw_dialog(&self) {
-        let (line, char) = match self.state {
-            GameState::Talking(line, char) => (line, char),
+        let char = match self.state {
+            GameState::Talking(_, char) => char,
             _ => return,
         };
-
-        let npcs = &self.maps[&self.current_map].npcs;
-        let npc = npcs.iter().find(|npc| npc.is_talking).unwrap();
-        let text: String = npc.dialogs[line][..char].iter().collect();
+        let node = match self.dialogue_node() {
+            Some(node) => node,
+            None => return,
+        };
+        let text: String = node.text[..char].iter().collect();
         let mut diag_box = self.diag_box();
         self.draw_diag_box(&self.textures["ui"]);
         let params = TextParams {
             font_size: 50,
-            font: Some(&self.font),
+            font: self.font.as_ref(),
             color: BLACK,
             ..Default::default()
         };
         diag_box.x += 5. * PIXEL;
+        if let Some(speaker) = &node.speaker {
+            let params = TextParams {
+                font_size: 40,
+                color: DARKGRAY,
+                ..params.clone()
+            };
+            draw_text_ex(speaker, diag_box.x, diag_box.y + 10. * PIXEL, params);
+            diag_box.y += 4. * PIXEL;
+        }
         render_text(diag_box, &text, params);
+
+        // Choices only show up once the line is done
+        if char + 1 < node.text.len() {
+            return;
+        }
+        let mouse_pos = self.input.mouse_pos;
+        for (index, (choice, rect)) in self.choices().iter().zip(self.choice_boxes()).enumerate() {
+            let color = match rect.contains(mouse_pos) {
+                true => Color::new(0.3, 0.3, 0.3, 0.9),
+                false => Color::new(0., 0., 0., 0.7),
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+            let params = TextParams {
+                font_size: 40,
+                font: self.font.as_ref(),
+                color: WHITE,
+                ..Default::default()
+            };
+            let label = format!("{}. {}", index + 1, choice.text);
+            draw_text_ex(
+                &label,
+                rect.x + 3. * PIXEL,
+                rect.bottom() - 4. * PIXEL,
+                params,
+            );
+        }
+    }
+
+    /// Where the choices of the current node go, stacked up above the dialog box
+    pub fn choice_boxes(&self) -> Vec<Rect> {
+        let count = self.choices().len();
+        let diag_box = self.diag_box();
+        let height = 0.6 * TILE;
+        (0..count)
+            .map(|index| {
+                let from_bottom = (count - index) as f32;
+                Rect::new(
+                    diag_box.x,
+                    diag_box.y - from_bottom * (height + PIXEL),
+                    diag_box.w,
+                    height,
+                )
+            })
+            .collect()
     }
 
     fn draw_diag_box(&self, texture: &Texture2D) {
@@ -265,7 +369,7 @@ impl Game {
 fn to_index(point: &u16, tile_size: f32) -> (f32, f32) {
     let x;
     let y;
-    if point % SHEET_SIZE == 0 {
+    if point.is_multiple_of(SHEET_SIZE) {
         x = SHEET_SIZE as f32 - 1.;
         y = (point / SHEET_SIZE) as f32 - 1.;
     } else {
@@ -343,7 +447,7 @@ fn draw_transition(screen: Rect, timer: &Timer) {
 }
 
 pub fn draw_tiles(
-    mesh: &Vec<Vec<u16>>,
+    mesh: &[Vec<u16>],
     origin: Vec2,
     texture: &Texture2D,
     screen: Option<Rect>,
@@ -361,7 +465,7 @@ pub fn draw_tiles(
             let params = gen_draw_params(cell, tile_size);
 
             draw_texture_ex(
-                &texture,
+                texture,
                 origin.x + col_num * TILE,
                 origin.y + row_num * TILE,
                 WHITE,
@@ -374,6 +478,63 @@ pub fn draw_tiles(
     }
 }
 
+/// Like draw_tiles, but every cell can come from a different tileset. Tiles bigger than a map
+/// tile stick out upwards and to the right, the same way Tiled draws them. Animated tiles show
+/// whichever frame they are on after clock seconds
+pub fn draw_map_tiles(
+    mesh: &[Vec<Tile>],
+    tilesets: &[Tileset],
+    textures: &Textures,
+    screen: Rect,
+    clock: f64,
+) {
+    for (row_num, row) in mesh.iter().enumerate() {
+        draw_tile_row(row, row_num, tilesets, textures, screen, clock)
+    }
+}
+
+fn draw_tile_row(
+    row: &[Tile],
+    row_num: usize,
+    tilesets: &[Tileset],
+    textures: &Textures,
+    screen: Rect,
+    clock: f64,
+) {
+    for (col_num, cell) in row.iter().enumerate() {
+        let draw_pos = vec2(col_num as f32 * TILE, row_num as f32 * TILE);
+        if !screen.contains(draw_pos) {
+            continue;
+        }
+        let tileset = match find_tileset(tilesets, cell.gid) {
+            Some(tileset) => tileset,
+            None => continue,
+        };
+        // Missing textures were already complained about when loading them
+        let texture = match textures.get(&tileset.texture) {
+            Some(texture) => texture,
+            None => continue,
+        };
+        let dest_size = tileset.tile_size * RATIO;
+        let (rotation, flip_x, flip_y) = cell.transform();
+        let params = DrawTextureParams {
+            dest_size: Some(dest_size * 1.01f32),
+            source: Some(tileset.source_rect(tileset.animated_gid(cell.gid, clock))),
+            rotation,
+            flip_x,
+            flip_y,
+            ..Default::default()
+        };
+        draw_texture_ex(
+            texture,
+            draw_pos.x,
+            draw_pos.y + TILE - dest_size.y,
+            WHITE,
+            params,
+        );
+    }
+}
+
 fn should_skip(point: Vec2, screen: Option<Rect>, cell: &u16) -> bool {
     if let Some(rect) = screen {
         if cell == &BLANK_TILE {
@@ -384,7 +545,7 @@ fn should_skip(point: Vec2, screen: Option<Rect>, cell: &u16) -> bool {
         }
         return false;
     }
-    return false;
+    false
 }
 
 fn diag_mesh() -> Vec<Vec<u16>> {
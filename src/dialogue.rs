@@ -0,0 +1,347 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::flags::{Action, Condition, World};
+use crate::logic::*;
+use crate::npc::NPC;
+
+/// What an NPC says, as a graph of nodes. GameState::Talking holds the index of the current node
+/// and how many of its characters are showing
+#[derive(Clone, Debug)]
+pub struct Dialogue {
+    pub start: Vec<Branch>,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    // Nothing is shown above the text when this is None
+    pub speaker: Option<Rc<str>>,
+    // Has an extra space at the end, see Dialogue::line
+    pub text: Vec<char>,
+    // Only used when there are no choices
+    pub next: Vec<Branch>,
+    pub choices: Vec<Choice>,
+    // Done as soon as the node shows up
+    pub actions: Vec<Action>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Choice {
+    pub text: String,
+    // Hidden unless all of these hold
+    pub conditions: Vec<Condition>,
+    pub next: Vec<Branch>,
+}
+
+/// Where to go next. The first branch whose conditions all hold is taken, and the conversation
+/// ends if there is none or it goes nowhere
+#[derive(Clone, Debug)]
+pub struct Branch {
+    pub conditions: Vec<Condition>,
+    pub to: Option<usize>,
+}
+
+impl Dialogue {
+    /// Either a graph ({ "start": "...", "nodes": { ... } }) or the old { "dialog": [...] } which
+    /// is read as one line after another
+    pub fn from_json(json: &Value) -> Result<Self, String> {
+        if let Some(lines) = json["dialog"].as_array() {
+            return Self::linear(lines);
+        }
+        let nodes = json["nodes"]
+            .as_object()
+            .ok_or("needs either a \"dialog\" array or a \"nodes\" table")?;
+        Self::graph(json, nodes)
+    }
+
+    fn linear(lines: &[Value]) -> Result<Self, String> {
+        let mut nodes = vec![];
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.as_str().ok_or("dialog lines have to be strings")?;
+            let next = match index + 1 < lines.len() {
+                true => vec![Branch::to(index + 1)],
+                false => vec![],
+            };
+            nodes.push(Node {
+                speaker: None,
+                text: Self::line(line),
+                next,
+                choices: vec![],
+                actions: vec![],
+            })
+        }
+        if nodes.is_empty() {
+            return Err("field \"dialog\" is empty".to_string());
+        }
+        Ok(Self {
+            start: vec![Branch::to(0)],
+            nodes,
+        })
+    }
+
+    fn graph(json: &Value, table: &Map<String, Value>) -> Result<Self, String> {
+        let ids: HashMap<&str, usize> = table
+            .keys()
+            .enumerate()
+            .map(|(index, id)| (id.as_str(), index))
+            .collect();
+        // Node ids get turned into indices here so a typo can't show up mid conversation
+        let find = |id: &str| match ids.get(id) {
+            Some(index) => Ok(*index),
+            None => Err(format!("there is no node called \"{id}\"")),
+        };
+        // Either a node id, or a list of { "if": ..., "next": ... } to pick from
+        let branches = |next: &Value| -> Result<Vec<Branch>, String> {
+            match next {
+                Value::Null => Ok(vec![]),
+                Value::String(id) => Ok(vec![Branch::to(find(id)?)]),
+                Value::Array(branches) => branches
+                    .iter()
+                    .map(|branch| {
+                        let to = match &branch["next"] {
+                            Value::Null => None,
+                            Value::String(id) => Some(find(id)?),
+                            _ => return Err("node ids have to be strings".to_string()),
+                        };
+                        Ok(Branch {
+                            conditions: conditions(&branch["if"])?,
+                            to,
+                        })
+                    })
+                    .collect(),
+                _ => Err("\"next\" has to be a node id or a list of branches".to_string()),
+            }
+        };
+
+        let mut nodes = vec![];
+        for (id, node) in table {
+            let text = node["text"]
+                .as_str()
+                .ok_or(format!("node \"{id}\" has no text"))?;
+            let mut choices = vec![];
+            for choice in node["choices"].as_array().into_iter().flatten() {
+                let text = choice["text"]
+                    .as_str()
+                    .ok_or(format!("a choice in node \"{id}\" has no text"))?;
+                choices.push(Choice {
+                    text: text.to_string(),
+                    conditions: conditions(&choice["if"])?,
+                    next: branches(&choice["next"])?,
+                })
+            }
+            let actions = strings(&node["do"])?
+                .into_iter()
+                .map(Action::parse)
+                .collect::<Result<_, _>>()?;
+            nodes.push(Node {
+                speaker: node["speaker"].as_str().map(|speaker| speaker.into()),
+                text: Self::line(text),
+                next: branches(&node["next"])?,
+                choices,
+                actions,
+            })
+        }
+        let start = branches(&json["start"])?;
+        if start.is_empty() {
+            return Err("field \"start\" is missing".to_string());
+        }
+        Ok(Self { start, nodes })
+    }
+
+    /// Every item a condition anywhere in here asks about, so validate-maps can check them
+    pub fn items(&self) -> Vec<&str> {
+        self.conditions().filter_map(Condition::item).collect()
+    }
+
+    /// Every quest a condition or an action names
+    pub fn quests(&self) -> Vec<&str> {
+        let actions = self.nodes.iter().flat_map(|node| &node.actions);
+        let actions = actions.filter_map(Action::quest);
+        self.conditions()
+            .filter_map(Condition::quest)
+            .chain(actions)
+            .collect()
+    }
+
+    fn conditions(&self) -> impl Iterator<Item = &Condition> {
+        let branches = self.nodes.iter().flat_map(|node| {
+            let choices = node.choices.iter().flat_map(|choice| &choice.next);
+            node.next.iter().chain(choices)
+        });
+        self.start
+            .iter()
+            .chain(branches)
+            .flat_map(|branch| &branch.conditions)
+            .chain(
+                self.nodes
+                    .iter()
+                    .flat_map(|node| node.choices.iter().flat_map(|choice| &choice.conditions)),
+            )
+    }
+
+    // The space lets the whole line be sliced with [..len - 1] while it's being typed out
+    fn line(text: &str) -> Vec<char> {
+        let mut chars: Vec<char> = text.chars().collect();
+        chars.push(' ');
+        chars
+    }
+}
+
+impl Branch {
+    fn to(node: usize) -> Self {
+        Self {
+            conditions: vec![],
+            to: Some(node),
+        }
+    }
+
+    /// The node the first branch that holds leads to
+    pub fn pick(branches: &[Branch], world: World) -> Option<usize> {
+        let branch = branches.iter().find(|branch| {
+            branch
+                .conditions
+                .iter()
+                .all(|condition| condition.check(world))
+        })?;
+        branch.to
+    }
+}
+
+// A single string or a list of them
+fn strings(json: &Value) -> Result<Vec<&str>, String> {
+    match json {
+        Value::Null => Ok(vec![]),
+        Value::String(string) => Ok(vec![string]),
+        Value::Array(strings) => strings
+            .iter()
+            .map(|string| string.as_str().ok_or("expected a string".to_string()))
+            .collect(),
+        _ => Err("expected a string or a list of them".to_string()),
+    }
+}
+
+fn conditions(json: &Value) -> Result<Vec<Condition>, String> {
+    strings(json)?.into_iter().map(Condition::parse).collect()
+}
+
+impl Game {
+    /// Starts talking to whoever is marked as talking
+    pub fn start_dialogue(&mut self) {
+        let start = match self.talking_npc() {
+            Some(npc) => npc.dialogue.start.clone(),
+            None => return,
+        };
+        self.follow(&start)
+    }
+
+    /// Finishes the line if it's still being typed out, otherwise moves on to the next node. Nodes
+    /// with choices only move on once one is picked, counting only the ones that are shown
+    pub fn advance_dialogue(&mut self, choice: Option<usize>) {
+        let (node, char) = match self.state {
+            GameState::Talking(node, char) => (node, char),
+            _ => return,
+        };
+        let current = match self.dialogue_node() {
+            Some(current) => current,
+            None => {
+                self.state = GameState::Normal;
+                return;
+            }
+        };
+        let max_char = current.text.len() - 1;
+        if char < max_char {
+            self.state = GameState::Talking(node, max_char);
+            return;
+        }
+        let next = match current.choices.is_empty() {
+            true => current.next.clone(),
+            false => match choice.and_then(|choice| self.choices().get(choice).copied()) {
+                Some(choice) => choice.next.clone(),
+                None => return,
+            },
+        };
+        self.follow(&next)
+    }
+
+    // Goes to wherever the branches lead, ending the conversation if that's nowhere
+    fn follow(&mut self, branches: &[Branch]) {
+        let next = Branch::pick(branches, self.world());
+        let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        let npc = match current_map.npcs.iter_mut().find(|npc| npc.is_talking) {
+            Some(npc) => npc,
+            None => return,
+        };
+        let node = match next.and_then(|next| npc.dialogue.nodes.get(next)) {
+            Some(node) => node,
+            None => {
+                npc.is_talking = false;
+                let opens_shop = match self.state {
+                    GameState::Talking(node, _) => {
+                        npc.dialogue.nodes[node].actions.contains(&Action::OpenShop)
+                    }
+                    _ => false,
+                };
+                self.state = GameState::Normal;
+                if opens_shop {
+                    let stock = npc.shop.clone();
+                    self.open_shop(&stock);
+                }
+                return;
+            }
+        };
+        for action in node.actions.clone() {
+            self.run_action(&action)
+        }
+        self.state = GameState::Talking(next.unwrap(), 0)
+    }
+
+    pub fn run_action(&mut self, action: &Action) {
+        match action {
+            Action::StartQuest(quest) => self.start_quest(quest),
+            Action::FinishQuest(quest) => self.finish_quest(quest),
+            action => self.flags.run(action),
+        }
+    }
+
+    pub fn world(&self) -> World<'_> {
+        World {
+            flags: &self.flags,
+            inventory: &self.player.inventory,
+            quests: &self.quests,
+        }
+    }
+
+    pub fn talking_npc(&self) -> Option<&NPC> {
+        let npcs = &self.maps[&self.current_map].npcs;
+        npcs.iter().find(|npc| npc.is_talking)
+    }
+
+    /// The node being shown right now, if anyone is talking
+    pub fn dialogue_node(&self) -> Option<&Node> {
+        let node = match self.state {
+            GameState::Talking(node, _) => node,
+            _ => return None,
+        };
+        self.talking_npc()?.dialogue.nodes.get(node)
+    }
+
+    /// The choices of the current node that pass their conditions
+    pub fn choices(&self) -> Vec<&Choice> {
+        let node = match self.dialogue_node() {
+            Some(node) => node,
+            None => return vec![],
+        };
+        let world = self.world();
+        node.choices
+            .iter()
+            .filter(|choice| {
+                choice
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.check(world))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+
+use crate::logic::Timer;
+use crate::map::MapErrorKind;
+use crate::player::{Player, PLAYER_HEALTH};
+use crate::ui::items::ItemType;
+
+/// What using a consumable does, from the "effects" list of an item in items.json
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    Heal(f32),
+    Buff(BuffKind, f32, f32),
+    // Gets rid of every buff that makes things worse
+    Cure,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuffKind {
+    Speed,
+    Damage,
+}
+
+/// A temporary multiplier on the player, below 1 makes it a debuff
+#[derive(Clone, Debug)]
+pub struct Buff {
+    pub kind: BuffKind,
+    pub multiplier: f32,
+    pub timer: Timer,
+}
+
+impl Effect {
+    /// Looks like { "type": "speed", "multiplier": 1.5, "duration": 10 }, durations in seconds
+    pub fn from_json(json: &Value) -> Result<Self, MapErrorKind> {
+        let number = |field: &'static str| {
+            json[field]
+                .as_f64()
+                .map(|num| num as f32)
+                .ok_or(MapErrorKind::Field(field))
+        };
+        let kind = match json["type"].as_str() {
+            Some("heal") => return Ok(Effect::Heal(number("amount")?)),
+            Some("cure") => return Ok(Effect::Cure),
+            Some("speed") => BuffKind::Speed,
+            Some("damage") => BuffKind::Damage,
+            _ => return Err(MapErrorKind::Field("effects")),
+        };
+        Ok(Effect::Buff(
+            kind,
+            number("multiplier")?,
+            number("duration")?,
+        ))
+    }
+}
+
+impl Player {
+    /// Uses up one of the consumable in that slot, false if there's nothing there to use
+    pub fn use_slot(&mut self, index: usize) -> bool {
+        let item = match self.inventory.content.get_mut(index) {
+            Some(Some(item)) if item.def.class == ItemType::Consumable => item,
+            _ => return false,
+        };
+        let def = item.def.clone();
+        item.count -= 1;
+        if item.count == 0 {
+            self.inventory.content[index] = None;
+        }
+        for effect in &def.effects {
+            self.apply(effect)
+        }
+        true
+    }
+
+    pub fn apply(&mut self, effect: &Effect) {
+        match effect {
+            Effect::Heal(amount) => {
+                self.props.health = (self.props.health + amount).min(PLAYER_HEALTH);
+            }
+            Effect::Buff(kind, multiplier, duration) => {
+                // Drinking the same thing twice just restarts the clock
+                self.buffs
+                    .retain(|buff| buff.kind != *kind || buff.multiplier != *multiplier);
+                self.buffs.push(Buff {
+                    kind: *kind,
+                    multiplier: *multiplier,
+                    timer: Timer::new(*duration),
+                });
+            }
+            Effect::Cure => self.buffs.retain(|buff| buff.multiplier >= 1.),
+        }
+    }
+
+    pub fn tick_buffs(&mut self, dt: f32) {
+        for buff in self.buffs.iter_mut() {
+            buff.timer.tick(dt)
+        }
+        self.buffs.retain(|buff| !buff.timer.is_done());
+    }
+
+    /// Every active buff of that kind multiplied together
+    pub fn multiplier(&self, kind: BuffKind) -> f32 {
+        self.buffs
+            .iter()
+            .filter(|buff| buff.kind == kind)
+            .map(|buff| buff.multiplier)
+            .product()
+    }
+}
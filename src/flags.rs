@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::quests::{QuestLog, QuestState};
+use crate::ui::inventory::Inventory;
+
+/// Story state anything can check or change. A flag is just a counter, set means not 0
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Flags {
+    pub values: HashMap<Rc<str>, i64>,
+}
+
+/// Everything a condition can look at
+#[derive(Clone, Copy)]
+pub struct World<'a> {
+    pub flags: &'a Flags,
+    pub inventory: &'a Inventory,
+    pub quests: &'a QuestLog,
+}
+
+/// Something a dialogue checks before showing a line or a choice
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    // At least this many of the item anywhere in the inventory
+    HasItem(Rc<str>, u32),
+    Flag(Rc<str>),
+    Counter(Rc<str>, Compare, i64),
+    Quest(Rc<str>, QuestState),
+    Not(Box<Condition>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compare {
+    Less,
+    LessOrEqual,
+    Equal,
+    MoreOrEqual,
+    More,
+}
+
+/// A change to the flags or the quests, done when a dialogue node is reached
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Set(Rc<str>, i64),
+    Add(Rc<str>, i64),
+    StartQuest(Rc<str>),
+    // Does nothing unless every objective is done
+    FinishQuest(Rc<str>),
+    // Opens the NPC's shop once the conversation ends on this node
+    OpenShop,
+}
+
+impl Flags {
+    pub fn get(&self, name: &str) -> i64 {
+        self.values.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.get(name) != 0
+    }
+
+    pub fn set(&mut self, name: &str, value: i64) {
+        match value {
+            // Keeps saves from filling up with zeroes
+            0 => self.values.remove(name),
+            value => self.values.insert(name.into(), value),
+        };
+    }
+
+    pub fn add(&mut self, name: &str, amount: i64) {
+        self.set(name, self.get(name) + amount)
+    }
+
+    pub fn run(&mut self, action: &Action) {
+        match action {
+            Action::Set(name, value) => self.set(name, *value),
+            Action::Add(name, amount) => self.add(name, *amount),
+            // These need the whole game, see Game::run_action
+            Action::StartQuest(_) | Action::FinishQuest(_) | Action::OpenShop => (),
+        }
+    }
+}
+
+impl Condition {
+    /// One of "has black_sword", "has slime 3", "flag dungeon_cleared", "counter kills >= 3",
+    /// "quest visit_lake ready" (new, active, ready or finished), or any of those after "not"
+    pub fn parse(condition: &str) -> Result<Self, String> {
+        let bad = || format!("can't make sense of the condition \"{condition}\"");
+        let words: Vec<&str> = condition.split_whitespace().collect();
+        let parsed = match words.as_slice() {
+            ["not", ..] => {
+                let rest = condition.trim_start().trim_start_matches("not");
+                Condition::Not(Box::new(Self::parse(rest)?))
+            }
+            ["has", item] => Condition::HasItem((*item).into(), 1),
+            ["has", item, count] => {
+                Condition::HasItem((*item).into(), count.parse().map_err(|_| bad())?)
+            }
+            ["flag", name] => Condition::Flag((*name).into()),
+            ["counter", name, compare, value] => {
+                let compare = match *compare {
+                    "<" => Compare::Less,
+                    "<=" => Compare::LessOrEqual,
+                    "==" => Compare::Equal,
+                    ">=" => Compare::MoreOrEqual,
+                    ">" => Compare::More,
+                    _ => return Err(bad()),
+                };
+                Condition::Counter((*name).into(), compare, value.parse().map_err(|_| bad())?)
+            }
+            ["quest", id, state] => {
+                let state = match *state {
+                    "new" => QuestState::New,
+                    "active" => QuestState::Active,
+                    "ready" => QuestState::Ready,
+                    "finished" => QuestState::Finished,
+                    _ => return Err(bad()),
+                };
+                Condition::Quest((*id).into(), state)
+            }
+            _ => return Err(bad()),
+        };
+        Ok(parsed)
+    }
+
+    /// The item it asks about, if any
+    pub fn item(&self) -> Option<&str> {
+        match self {
+            Condition::HasItem(item, _) => Some(item),
+            Condition::Not(condition) => condition.item(),
+            _ => None,
+        }
+    }
+
+    /// The quest it asks about, if any
+    pub fn quest(&self) -> Option<&str> {
+        match self {
+            Condition::Quest(id, _) => Some(id),
+            Condition::Not(condition) => condition.quest(),
+            _ => None,
+        }
+    }
+
+    pub fn check(&self, world: World) -> bool {
+        match self {
+            Condition::HasItem(item, count) => world.inventory.count(item) >= *count,
+            Condition::Flag(name) => world.flags.is_set(name),
+            Condition::Counter(name, compare, value) => {
+                let current = world.flags.get(name);
+                match compare {
+                    Compare::Less => current < *value,
+                    Compare::LessOrEqual => current <= *value,
+                    Compare::Equal => current == *value,
+                    Compare::MoreOrEqual => current >= *value,
+                    Compare::More => current > *value,
+                }
+            }
+            Condition::Quest(id, state) => world.quests.state(id) == *state,
+            Condition::Not(condition) => !condition.check(world),
+        }
+    }
+}
+
+impl Action {
+    /// "set dungeon_cleared" (which means 1), "set kills 0", "add kills 1", "start visit_lake",
+    /// "finish visit_lake" or "shop"
+    pub fn parse(action: &str) -> Result<Self, String> {
+        let bad = || format!("can't make sense of the action \"{action}\"");
+        let words: Vec<&str> = action.split_whitespace().collect();
+        let parsed = match words.as_slice() {
+            ["set", name] => Action::Set((*name).into(), 1),
+            ["set", name, value] => Action::Set((*name).into(), value.parse().map_err(|_| bad())?),
+            ["add", name, amount] => {
+                Action::Add((*name).into(), amount.parse().map_err(|_| bad())?)
+            }
+            ["start", quest] => Action::StartQuest((*quest).into()),
+            ["finish", quest] => Action::FinishQuest((*quest).into()),
+            ["shop"] => Action::OpenShop,
+            _ => return Err(bad()),
+        };
+        Ok(parsed)
+    }
+
+    pub fn quest(&self) -> Option<&str> {
+        match self {
+            Action::StartQuest(quest) | Action::FinishQuest(quest) => Some(quest),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::input::InputFrame;
+use crate::logic::*;
+use crate::map::MapLoadError;
+
+/// Steps a Game without ever opening a window. Nothing gets drawn and no textures are loaded, but
+/// the maps, NPC dialogs and everything Game::tick touches are the real ones. Like the game
+/// itself, this expects assets/ to be in the working directory. Each step is one tick, so frames
+/// should use timestep::FIXED_DT to behave like the real game.
+pub struct Headless {
+    pub game: Game,
+    pub frames: u32,
+}
+
+impl Headless {
+    pub fn new() -> Result<Self, Vec<MapLoadError>> {
+        let mut game = Game::new(HashMap::new(), None)?;
+        // Skip the main menu
+        game.state = GameState::Normal;
+        Ok(Self { game, frames: 0 })
+    }
+
+    pub fn step(&mut self, input: InputFrame) {
+        self.game.tick(input);
+        self.frames += 1;
+    }
+
+    /// Feeds the same input for a number of frames
+    pub fn run(&mut self, input: &InputFrame, frames: u32) {
+        for _ in 0..frames {
+            self.step(input.clone());
+        }
+    }
+
+    /// Feeds the same input until the condition holds, returns false if it never did within
+    /// max_frames
+    pub fn run_until<F>(&mut self, input: &InputFrame, max_frames: u32, condition: F) -> bool
+    where
+        F: Fn(&Game) -> bool,
+    {
+        for _ in 0..max_frames {
+            if condition(&self.game) {
+                return true;
+            }
+            self.step(input.clone());
+        }
+        condition(&self.game)
+    }
+}
@@ -0,0 +1,152 @@
+use macroquad::prelude::*;
+use std::collections::HashSet;
+
+// The size of the window declared in window_conf, used whenever there is no real screen to ask
+pub const DEFAULT_SCREEN: Vec2 = vec2(800., 600.);
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+// macroquad has no way to list every key that is down, so anything the game reads goes here
+const TRACKED_KEYS: [KeyCode; 12] = [
+    KeyCode::W,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::Escape,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+];
+
+/// Everything the game logic is allowed to know about the outside world for a single frame.
+/// Game::tick only ever reads from this, so a frame can come from macroquad or be made up in a
+/// test.
+#[derive(Clone, Debug)]
+pub struct InputFrame {
+    pub keys_down: HashSet<KeyCode>,
+    pub keys_pressed: HashSet<KeyCode>,
+    pub keys_released: HashSet<KeyCode>,
+    pub mouse_down: HashSet<MouseButton>,
+    pub mouse_pressed: HashSet<MouseButton>,
+    pub mouse_released: HashSet<MouseButton>,
+    // In world space, not screen space
+    pub mouse_pos: Vec2,
+    pub screen_size: Vec2,
+    pub dt: f32,
+}
+
+impl InputFrame {
+    /// An empty frame where nothing is pressed
+    pub fn new(dt: f32) -> Self {
+        Self {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            mouse_down: HashSet::new(),
+            mouse_pressed: HashSet::new(),
+            mouse_released: HashSet::new(),
+            mouse_pos: vec2(0., 0.),
+            screen_size: DEFAULT_SCREEN,
+            dt,
+        }
+    }
+
+    /// Reads the current frame from macroquad. This needs a window to be open
+    pub fn capture(mouse_pos: Vec2) -> Self {
+        let mut frame = Self::new(get_frame_time());
+        for key in TRACKED_KEYS {
+            if is_key_down(key) {
+                frame.keys_down.insert(key);
+            }
+            if is_key_pressed(key) {
+                frame.keys_pressed.insert(key);
+            }
+            if is_key_released(key) {
+                frame.keys_released.insert(key);
+            }
+        }
+        for button in MOUSE_BUTTONS {
+            if is_mouse_button_down(button) {
+                frame.mouse_down.insert(button);
+            }
+            if is_mouse_button_pressed(button) {
+                frame.mouse_pressed.insert(button);
+            }
+            if is_mouse_button_released(button) {
+                frame.mouse_released.insert(button);
+            }
+        }
+        frame.mouse_pos = mouse_pos;
+        frame.screen_size = vec2(screen_width(), screen_height());
+        frame
+    }
+
+    /// Folds a newer frame into this one. What is held down gets replaced but presses and releases
+    /// pile up until clear_events, so a quick tap in between two ticks does not get lost
+    pub fn merge(&mut self, newer: InputFrame) {
+        self.keys_pressed.extend(newer.keys_pressed);
+        self.keys_released.extend(newer.keys_released);
+        self.mouse_pressed.extend(newer.mouse_pressed);
+        self.mouse_released.extend(newer.mouse_released);
+        self.keys_down = newer.keys_down;
+        self.mouse_down = newer.mouse_down;
+        self.mouse_pos = newer.mouse_pos;
+        self.screen_size = newer.screen_size;
+        self.dt = newer.dt;
+    }
+
+    pub fn clear_events(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.mouse_pressed.clear();
+        self.mouse_released.clear();
+    }
+
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn mouse_down(&self, button: MouseButton) -> bool {
+        self.mouse_down.contains(&button)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_pressed.contains(&button)
+    }
+
+    pub fn mouse_released(&self, button: MouseButton) -> bool {
+        self.mouse_released.contains(&button)
+    }
+
+    // Builder style helpers, mostly for the headless runner
+
+    pub fn hold(mut self, key: KeyCode) -> Self {
+        self.keys_down.insert(key);
+        self
+    }
+
+    pub fn press(mut self, key: KeyCode) -> Self {
+        self.keys_down.insert(key);
+        self.keys_pressed.insert(key);
+        self
+    }
+
+    pub fn click(mut self, button: MouseButton, pos: Vec2) -> Self {
+        self.mouse_down.insert(button);
+        self.mouse_pressed.insert(button);
+        self.mouse_pos = pos;
+        self
+    }
+}
@@ -1,9 +1,10 @@
 use crate::camera::TERRAIN_TILE_SIZE;
+use crate::input::InputFrame;
 use crate::logic::*;
 use crate::npc::overlay_pos;
 use crate::player::PIXEL;
-use crate::ui::items::{Item, ItemEntity};
 use macroquad::prelude::*;
+use std::rc::Rc;
 
 use super::{GameSignal, Interactables};
 
@@ -12,18 +13,29 @@ pub enum ChestState {
     Opening(Timer),
     Opened,
 }
+/// Both are names, looked up once the chest is opened
+#[derive(Clone, Debug)]
+pub enum ChestContent {
+    Item(Rc<str>),
+    Loot(Rc<str>),
+}
+
 pub struct Chest {
-    content: Item,
+    id: u64,
+    content: ChestContent,
     pos: Vec2,
     pub state: ChestState,
+    locked: bool,
 }
 
 impl Chest {
-    pub fn new(pos: Vec2, item: Item) -> Self {
+    pub fn new(id: u64, pos: Vec2, content: ChestContent) -> Self {
         Self {
-            content: item,
+            id,
+            content,
             pos,
             state: ChestState::Closed,
+            locked: false,
         }
     }
 
@@ -43,12 +55,12 @@ impl Chest {
         rect
     }
 
-    fn state_management(&mut self, search_box: &Rect) {
+    fn state_management(&mut self, search_box: &Rect, input: &InputFrame) {
         match self.state {
             ChestState::Opened | ChestState::Opening(_) => return,
             _ => (),
         }
-        if !is_key_pressed(KeyCode::R) {
+        if self.locked || !input.key_pressed(KeyCode::R) {
             return;
         }
         if !search_box.overlaps(&self.hitbox()) {
@@ -59,25 +71,29 @@ impl Chest {
 }
 
 impl Interactables for Chest {
-    fn activate(&mut self, search_box: &Rect) -> Option<GameSignal> {
-        self.state_management(search_box);
+    fn activate(&mut self, search_box: &Rect, input: &InputFrame) -> Option<GameSignal> {
+        self.state_management(search_box, input);
         let mut timer = match self.state {
             ChestState::Opening(timer) => timer,
             ChestState::Closed => return None,
             ChestState::Opened => return None,
         };
-        timer.tick();
+        timer.tick(input.dt);
 
         if !timer.is_done() {
             self.state = ChestState::Opening(timer);
             return None;
         }
         self.state = ChestState::Opened;
-        let item = ItemEntity::new(
-            self.content.clone(),
-            vec2(self.pos.x, self.pos.y + 10. * PIXEL),
-        );
-        return Some(GameSignal::SpawnItem(item));
+        let pos = vec2(self.pos.x, self.pos.y + 10. * PIXEL);
+        match &self.content {
+            ChestContent::Item(item) => Some(GameSignal::SpawnItem(item.clone(), pos)),
+            ChestContent::Loot(table) => {
+                // Centered under the chest rather than starting at its corner
+                let center = pos + vec2(self.hitbox().w / 2., 0.);
+                Some(GameSignal::SpawnLoot(table.clone(), center))
+            }
+        }
     }
 
     fn draw(&self, texture: &Texture2D) {
@@ -95,6 +111,9 @@ impl Interactables for Chest {
     }
 
     fn draw_overlay(&self, texture: &Texture2D) {
+        if self.locked {
+            return;
+        }
         if let ChestState::Opened = self.state {
             return;
         }
@@ -119,4 +138,24 @@ impl Interactables for Chest {
             17. * PIXEL,
         )
     }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn is_opened(&self) -> bool {
+        // Not while it's opening, the item only spawns once the timer runs out
+        matches!(self.state, ChestState::Opened)
+    }
+
+    fn set_opened(&mut self, opened: bool) {
+        self.state = match opened {
+            true => ChestState::Opened,
+            false => ChestState::Closed,
+        }
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.locked = locked
+    }
 }
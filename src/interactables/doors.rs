@@ -5,17 +5,21 @@ use crate::Rc;
 use crate::{TILE, TILE_SIZE};
 
 pub struct Door {
+    pub id: u64,
     pub hitbox: Rect,
     pub map: Rc<str>,
     pub location: Vec2,
+    locked: bool,
 }
 
 impl Door {
-    pub fn new(hitbox: Rect, map: &str, location: Vec2) -> Self {
+    pub fn new(id: u64, hitbox: Rect, map: &str, location: Vec2) -> Self {
         Self {
+            id,
             hitbox,
             map: map.into(),
             location,
+            locked: false,
         }
     }
 }
@@ -27,8 +31,12 @@ impl Interactables for Door {
         self.hitbox
     }
 
-    fn activate(&mut self, search_box: &Rect) -> Option<GameSignal> {
-        if !is_key_pressed(KeyCode::R) {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn activate(&mut self, search_box: &Rect, input: &InputFrame) -> Option<GameSignal> {
+        if self.locked || !input.key_pressed(KeyCode::R) {
             return None;
         }
 
@@ -37,10 +45,13 @@ impl Interactables for Door {
         }
 
         let trans = Transition::new(self.location, self.map.clone());
-        return Some(GameSignal::MovePlayer(trans));
+        Some(GameSignal::MovePlayer(trans))
     }
 
     fn draw_overlay(&self, texture: &Texture2D) {
+        if self.locked {
+            return;
+        }
         let dest_size = Some(vec2(TILE, TILE));
         let source = Some(Rect::new(TILE_SIZE, 0., TILE_SIZE, TILE_SIZE));
 
@@ -53,4 +64,8 @@ impl Interactables for Door {
 
         draw_texture_ex(texture, pos.x + 3. * PIXEL, pos.y - TILE, WHITE, draw_param);
     }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.locked = locked
+    }
 }
@@ -1,5 +1,7 @@
+use crate::input::InputFrame;
 use crate::Transition;
 use macroquad::prelude::*;
+use std::rc::Rc;
 
 pub mod chests;
 pub mod doors;
@@ -7,17 +9,27 @@ pub mod doors;
 pub use chests::*;
 pub use doors::*;
 
-use crate::ui::items::ItemEntity;
-
 #[derive(Clone)]
 pub enum GameSignal {
-    SpawnItem(ItemEntity),
+    // Drops one of the item with that id at the point
+    SpawnItem(Rc<str>, Vec2),
+    // Rolls the loot table and scatters whatever comes out around the point
+    SpawnLoot(Rc<str>, Vec2),
     MovePlayer(Transition),
 }
 
 pub trait Interactables {
-    fn activate(&mut self, search_box: &Rect) -> Option<GameSignal>;
+    fn activate(&mut self, search_box: &Rect, input: &InputFrame) -> Option<GameSignal>;
     fn draw(&self, texture: &Texture2D);
     fn draw_overlay(&self, texture: &Texture2D);
     fn hitbox(&self) -> Rect;
+    // The Tiled object id, which stays the same between runs so saves can refer to it
+    fn id(&self) -> u64;
+    // Only chests remember anything worth saving for now
+    fn is_opened(&self) -> bool {
+        false
+    }
+    fn set_opened(&mut self, _opened: bool) {}
+    // Locked ones can't be used, see Area::update_locks
+    fn set_locked(&mut self, _locked: bool) {}
 }
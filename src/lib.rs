@@ -0,0 +1,25 @@
+use logic::*;
+use std::rc::Rc;
+
+pub mod camera;
+pub mod dialogue;
+pub mod effects;
+pub mod flags;
+pub mod headless;
+pub mod input;
+pub mod interactables;
+pub mod logic;
+pub mod loot;
+pub mod map;
+pub mod monsters;
+pub mod navigation;
+pub mod npc;
+pub mod player;
+pub mod quests;
+pub mod save;
+pub mod spatial;
+pub mod tileset;
+pub mod timestep;
+pub mod ui;
+pub mod validate;
+pub mod weapons;
@@ -3,9 +3,17 @@ use std::fs::{read_dir, read_to_string};
 use std::path::PathBuf;
 use std::rc::Rc;
 
+use crate::effects::BuffKind;
+use crate::flags::Flags;
+use crate::input::InputFrame;
 use crate::interactables::GameSignal;
+use crate::loot::{load_loot_tables, scatter, unknown_items, LootTables};
 use crate::map::*;
+use crate::monsters::definition::{load_monsters, MonsterDefs};
 use crate::player::*;
+use crate::quests::{load_quests, unknown_names, Goal, QuestDefs, QuestLog};
+use crate::ui::inventory::HOTBAR;
+use crate::ui::items::{load_items, Item, ItemEntity, ItemRegistry};
 use crate::ui::*;
 use macroquad::experimental::animation::*;
 use macroquad::prelude::*;
@@ -21,12 +29,22 @@ pub type Maps = HashMap<Rc<str>, Area>;
 pub struct Game {
     pub player: Player,
     pub maps: Maps,
+    pub monster_defs: MonsterDefs,
+    pub loot_tables: LootTables,
+    pub item_defs: ItemRegistry,
+    pub flags: Flags,
+    pub quest_defs: QuestDefs,
+    pub quests: QuestLog,
     pub current_map: Rc<str>,
     pub cam_offset: Vec2,
     pub textures: Textures,
     pub state: GameState,
     pub tasks: Vec<GameSignal>, // This is kind of a hack
-    pub font: Font,
+    // None when running headless, since a font can't be loaded without a window
+    pub font: Option<Font>,
+    pub input: InputFrame,
+    // Seconds since the game started, for animated map tiles
+    pub anim_clock: f64,
 }
 
 #[derive(Clone, Debug)]
@@ -37,9 +55,12 @@ pub struct Transition {
     map: Rc<str>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub enum GUIType {
     Inventory,
+    QuestLog,
+    Shop(Shop),
     MainMenu(MainMenu),
     DeathScreen(DeathScreen),
 }
@@ -49,6 +70,7 @@ pub enum GameState {
     Normal,
     Quit,
     GUI(GUIType),
+    // The dialogue node and how many characters of it are showing
     Talking(usize, usize),
     Transition(Transition),
 }
@@ -78,8 +100,8 @@ impl Timer {
         }
     }
 
-    pub fn tick(&mut self) {
-        self.time -= get_frame_time();
+    pub fn tick(&mut self, dt: f32) {
+        self.time -= dt;
     }
 
     pub fn is_done(&self) -> bool {
@@ -96,82 +118,135 @@ impl Timer {
 }
 
 impl Game {
-    pub fn new(textures: Textures, font: Font) -> Self {
-        let mut area: Maps = HashMap::new();
+    /// Fails with every problem found in every map, so they can all be fixed in one go
+    pub fn new(textures: Textures, font: Option<Font>) -> Result<Self, Vec<MapLoadError>> {
         // TODO unhardcode this value
         let current_map: Rc<str> = "Room".into();
 
-        let map_list = get_path("assets/maps/", ".json");
-        for map in map_list {
-            let json_string = read_to_string(map).unwrap();
-            let map_content = Area::from(&json_string);
-            area.insert(map_content.0, map_content.1);
+        // All are loaded before failing so every broken file shows up at once
+        let (area, monster_defs, loot_tables, item_defs, quest_defs) = match (
+            load_maps(),
+            load_monsters(),
+            load_loot_tables(),
+            load_items(),
+            load_quests(),
+        ) {
+            (Ok(area), Ok(monster_defs), Ok(loot_tables), Ok(item_defs), Ok(quest_defs)) => {
+                (area, monster_defs, loot_tables, item_defs, quest_defs)
+            }
+            (area, monster_defs, loot_tables, item_defs, quest_defs) => {
+                let mut errors = area.err().unwrap_or_default();
+                errors.extend(monster_defs.err().unwrap_or_default());
+                errors.extend(loot_tables.err().unwrap_or_default());
+                errors.extend(item_defs.err().unwrap_or_default());
+                errors.extend(quest_defs.err().unwrap_or_default());
+                return Err(errors);
+            }
+        };
+        let mut errors = unknown_items(&loot_tables, &item_defs);
+        let map_names: Vec<&str> = area.keys().map(|name| &**name).collect();
+        errors.extend(unknown_names(
+            &quest_defs,
+            &item_defs,
+            &monster_defs,
+            &map_names,
+        ));
+        if !errors.is_empty() {
+            return Err(errors);
         }
         let state = GameState::GUI(GUIType::MainMenu(MainMenu::new()));
 
-        Game {
+        Ok(Game {
             player: Player::new(current_map.clone()),
             tasks: vec![],
             maps: area,
+            monster_defs,
+            loot_tables,
+            item_defs,
+            flags: Flags::default(),
+            quest_defs,
+            quests: QuestLog::default(),
             current_map,
             textures,
             cam_offset: vec2(0., 0.),
             state,
             font,
-        }
+            input: InputFrame::new(0.),
+            anim_clock: 0.,
+        })
     }
 
     fn key_event_handler(&mut self) {
-        if is_key_pressed(KeyCode::E) {
+        if self.input.key_pressed(KeyCode::E) {
             self.state = GameState::GUI(GUIType::Inventory)
         }
 
-        if is_key_pressed(KeyCode::Escape) {
-            let gui = match &self.state {
-                GameState::GUI(gui) => gui,
-                _ => return,
-            };
-            match gui {
-                GUIType::Inventory => self.state = GameState::Normal,
+        if self.input.key_pressed(KeyCode::Q) {
+            match self.state {
+                GameState::Normal => self.state = GameState::GUI(GUIType::QuestLog),
+                GameState::GUI(GUIType::QuestLog) => self.state = GameState::Normal,
+                _ => (),
+            }
+        }
+
+        if self.input.key_pressed(KeyCode::Escape) {
+            match &self.state {
+                GameState::GUI(GUIType::Inventory | GUIType::QuestLog | GUIType::Shop(_)) => {
+                    self.state = GameState::Normal
+                }
+                // Pause, so the game can be saved from the menu
+                GameState::Normal => {
+                    self.state = GameState::GUI(GUIType::MainMenu(MainMenu::new()));
+                    return;
+                }
                 _ => return,
             }
         }
 
-        if is_key_pressed(KeyCode::R) {
+        for (index, key) in HOTBAR.iter().enumerate() {
+            if !self.input.key_pressed(*key) {
+                continue;
+            }
+            // The number keys pick a choice while talking
+            match self.state {
+                GameState::Normal => {
+                    self.player.use_slot(index);
+                }
+                GameState::Talking(..) => self.advance_dialogue(Some(index)),
+                _ => (),
+            }
+        }
+
+        if self.input.key_pressed(KeyCode::R) {
             if let GameState::Talking(..) = self.state {
                 return;
             }
             self.talk_to_npc();
         }
 
-        if is_mouse_button_pressed(MouseButton::Left) {
+        if self.input.mouse_pressed(MouseButton::Left) {
             match self.state {
-                GameState::Talking(mut line, mut char) => {
-                    let npcs = &self.maps[&self.current_map].npcs;
-                    let npc = npcs.iter().find(|npc| npc.is_talking).unwrap();
-
-                    let max_char = npc.dialogs[line].len() - 1;
-                    if char >= max_char {
-                        line += 1;
-                        char = 0;
-                    } else {
-                        char = max_char;
-                    }
-                    self.state = GameState::Talking(line, char)
+                GameState::Talking(..) => {
+                    let mouse_pos = self.input.mouse_pos;
+                    let choice = self
+                        .choice_boxes()
+                        .iter()
+                        .position(|choice| choice.contains(mouse_pos));
+                    self.advance_dialogue(choice)
                 }
                 GameState::Normal => {
                     if let PlayerState::Attacking(..) = self.player.state {
                         return;
                     }
                     // If the player has no weapon
-                    if let None = self.player.inventory.content[12] {
+                    if self.player.inventory.content[12].is_none() {
                         return;
                     }
-                    let mouse_pos = self.get_mouse_pos();
+                    let mouse_pos = self.input.mouse_pos;
                     self.player.face(mouse_pos);
                     self.player.attack(mouse_pos);
                 }
-                _ => return,
+                _ => (),
             }
         }
     }
@@ -186,67 +261,58 @@ impl Game {
             if !npc.hitbox.overlaps(&search_box) {
                 continue;
             }
-            self.state = GameState::Talking(0, 0);
             npc.is_talking = true;
-            npc.face(self.player.pos())
+            npc.face(self.player.pos());
+            // Counted before the dialogue starts so it can already tell the quest is done
+            let talk = Goal::Talk(npc.name.clone());
+            self.quests.progress(&talk, 1);
+            self.start_dialogue();
+            break;
         }
     }
 
     // I can not think of a better name for the love of god
     fn conversation(&mut self) {
-        let (line, mut char) = match self.state {
-            GameState::Talking(line, char) => (line, char),
+        let (node, char) = match self.state {
+            GameState::Talking(node, char) => (node, char),
             _ => return,
         };
-        let current_map = self.maps.get_mut(&self.current_map).unwrap();
-        let talking_npc = current_map
-            .npcs
-            .iter_mut()
-            .find(|npc| npc.is_talking)
-            .unwrap();
-
-        match talking_npc.dialogs.get(line) {
-            Some(_) => {
-                if talking_npc.dialogs[line].len() <= char {
-                    char = talking_npc.dialogs[line].len() - 1;
-                } else {
-                    char += 1;
-                }
-                self.state = GameState::Talking(line, char)
-            }
+        // Types the line out one character a tick
+        let max_char = match self.dialogue_node() {
+            Some(current) => current.text.len() - 1,
             None => {
                 self.state = GameState::Normal;
-                talking_npc.is_talking = false
+                return;
             }
-        }
+        };
+        self.state = GameState::Talking(node, (char + 1).min(max_char))
     }
 
-    pub fn tick(&mut self) {
-        self.new_camera_offset();
+    pub fn tick(&mut self, input: InputFrame) {
+        self.input = input;
+        self.save_positions();
         self.key_event_handler();
-        self.anim_tick();
         match self.state.clone() {
             GameState::Talking(..) => {
                 self.player.change_anim(false);
                 self.conversation();
-                return;
             }
             GameState::Transition(mut transition) => {
                 self.player.change_anim(false);
                 self.timer_progress(&mut transition);
-                return;
             }
             GameState::GUI(_) => {
                 self.player.change_anim(false);
                 self.tick_gui();
-                return;
             }
-            GameState::Normal | GameState::Quit => (),
+            GameState::Normal | GameState::Quit => {
+                self.tick_player();
+                self.tick_map();
+                self.do_task();
+            }
         }
-
-        self.tick_player();
-        self.tick_map();
-        self.do_task();
+        // Items come from the ground, rewards and the shop, so just look at what's there
+        self.quests.count_items(&self.player.inventory);
     }
 
     fn do_task(&mut self) {
@@ -257,40 +323,61 @@ impl Game {
     }
 
     fn tick_map(&mut self) {
+        let dt = self.input.dt;
         let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        let monster_index = current_map.index_monsters();
+        let mut targets = Targets {
+            monsters: &mut current_map.enemies,
+            monster_index: &monster_index,
+            player: &mut self.player,
+            walls: &current_map.walls,
+        };
         current_map
             .projectiles
             .iter_mut()
-            .for_each(|proj| proj.tick(&mut current_map.enemies));
+            .for_each(|proj| proj.tick(&mut targets, dt));
+
+        current_map.enemies.iter_mut().for_each(|enemy| {
+            let projectiles = &mut current_map.projectiles;
+            enemy.tick(
+                &mut self.player,
+                &current_map.walls,
+                &current_map.nav,
+                projectiles,
+                dt,
+            )
+        });
+        current_map.separate_monsters();
 
-        current_map
-            .enemies
-            .iter_mut()
-            .for_each(|enemy| enemy.tick(&mut self.player, &current_map.walls));
-
-        current_map
-            .spawners
-            .iter_mut()
-            .for_each(|spawner| spawner.tick(&mut current_map.enemies));
+        let player_hitbox = self.player.hitbox();
+        current_map.tick_npcs(player_hitbox, dt);
+        current_map.tick_spawners(player_hitbox, &self.monster_defs, dt);
+        current_map.tick_bosses(player_hitbox, &self.monster_defs, &mut self.flags);
 
         for item in current_map.items.iter_mut() {
             if !item.hitbox.overlaps(&self.player.hitbox()) {
                 continue;
             }
-            self.player.inventory.append(item.item.clone());
-            item.should_delete = true
+            // Whatever doesn't fit stays on the ground
+            match self.player.inventory.append(item.item.clone()) {
+                Some(rest) => item.item = rest,
+                None => item.should_delete = true,
+            }
         }
         let search_box = self.player.search_box();
 
         for interactable in current_map.interactables.iter_mut() {
-            let signal = interactable.activate(&search_box);
+            let signal = interactable.activate(&search_box, &self.input);
             if let Some(signal) = signal {
                 self.tasks.push(signal)
             }
         }
 
-        let player_hitbox = self.player.hitbox();
+        let sealed = current_map.is_sealed();
         for gate in &current_map.gates {
+            if sealed {
+                break;
+            }
             if !gate.hitbox().overlaps(&player_hitbox) {
                 continue;
             }
@@ -298,13 +385,24 @@ impl Game {
             self.player.state = PlayerState::Transition;
         }
 
-        current_map.clean_up();
+        for monster in current_map.clean_up(&self.loot_tables, &self.item_defs) {
+            self.quests.progress(&Goal::Kill(monster), 1);
+        }
     }
 
     fn handle_signals(&mut self, signal: &GameSignal) {
         let current_map = self.maps.get_mut(&self.current_map).unwrap();
         match signal {
-            GameSignal::SpawnItem(item) => current_map.items.push(item.clone()),
+            GameSignal::SpawnItem(key, pos) => match Item::from_key(&self.item_defs, key, 1) {
+                Some(item) => current_map.items.push(ItemEntity::new(item, *pos)),
+                None => warn!("unknown item {key}"),
+            },
+            GameSignal::SpawnLoot(table, pos) => match self.loot_tables.get(table) {
+                Some(table) => current_map
+                    .items
+                    .extend(scatter(table.roll(&self.item_defs), *pos)),
+                None => warn!("unknown loot table {table}"),
+            },
             GameSignal::MovePlayer(trans) => self.state = GameState::Transition(trans.clone()),
         }
     }
@@ -324,12 +422,20 @@ impl Game {
             }
             GameState::Normal | GameState::Quit => (),
         }
-        let mouse_pos = self.get_mouse_pos();
+        let mouse_pos = self.input.mouse_pos;
         let current_map = self.maps.get_mut(&self.current_map).unwrap();
-        self.player.tick(mouse_pos);
+        self.player.tick(&self.input);
         self.player.wall_collsion(&current_map.walls);
+        // NPCs are not walls since they could move around
+        let npcs: Vec<Rect> = current_map.npcs.iter().map(|npc| npc.hitbox).collect();
+        self.player.collide(&npcs);
+        // Gates turn into walls until the boss is dead
+        if current_map.is_sealed() {
+            let gates: Vec<Rect> = current_map.gates.iter().map(|gate| gate.hitbox()).collect();
+            self.player.collide(&gates);
+        }
 
-        if is_mouse_button_released(MouseButton::Right) {
+        if self.input.mouse_released(MouseButton::Right) {
             current_map
                 .projectiles
                 .push(self.player.current_projectile(mouse_pos))
@@ -354,7 +460,7 @@ impl Game {
     }
 
     fn timer_progress(&mut self, transition: &mut Transition) {
-        transition.timer.tick();
+        transition.timer.tick(self.input.dt);
         self.transition(transition);
 
         if transition.timer.is_done() {
@@ -365,6 +471,31 @@ impl Game {
         }
     }
 
+    fn save_positions(&mut self) {
+        self.player.props.save_pos();
+        let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        for monster in current_map.enemies.iter_mut() {
+            monster.get_mut_props().save_pos();
+        }
+    }
+
+    /// Everything that should happen once per drawn frame rather than once per tick. alpha is how
+    /// far we are in between the last two ticks
+    pub fn prepare_frame(&mut self, alpha: f32, dt: f32) {
+        self.player.props.interpolate(alpha);
+        let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        for monster in current_map.enemies.iter_mut() {
+            monster.get_mut_props().interpolate(alpha);
+        }
+        for projectile in current_map.projectiles.iter_mut() {
+            projectile.interpolate(alpha);
+        }
+        self.new_camera_offset(dt);
+        self.anim_clock += dt as f64;
+        self.anim_tick();
+    }
+
+    // Purely cosmetic, so this is driven by the render loop rather than by tick
     fn anim_tick(&mut self) {
         self.player.props.animation.update();
 
@@ -379,10 +510,6 @@ impl Game {
         }
     }
 
-    fn get_monster_list(&mut self) -> &mut [Monster] {
-        &mut self.maps.get_mut(&self.current_map).unwrap().enemies
-    }
-
     fn damage_monster(&mut self, attack: &mut Attack) {
         let prog = attack.timer.progress();
         if prog < 0.5 {
@@ -392,10 +519,12 @@ impl Game {
             return;
         }
         let damage_zone = self.player.weapon_hitbox();
-        let damage = self.player.held_weapon.base_damage;
+        let damage = self.player.held_weapon.base_damage * self.player.multiplier(BuffKind::Damage);
         let player_pos = self.player.pos();
 
-        for monster in self.get_monster_list() {
+        let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        for index in current_map.index_monsters().query(damage_zone) {
+            let monster = &mut current_map.enemies[index];
             if !damage_zone.overlaps(&monster.hitbox()) {
                 continue;
             }
@@ -416,23 +545,59 @@ impl Game {
     }
 
     fn move_map(&mut self, pos: Vec2, map: Rc<str>) {
+        if let Some(old_map) = self.maps.get_mut(&self.current_map) {
+            old_map.abandon_fights();
+        }
         self.player.props.pos = pos;
+        self.player.props.snap();
+        self.quests.progress(&Goal::Reach(map.clone()), 1);
         self.current_map = map;
     }
 }
 
-pub fn get_path(dir: &str, file_type: &str) -> Vec<PathBuf> {
-    let maps = read_dir(dir).unwrap();
+pub fn load_maps() -> Result<Maps, Vec<MapLoadError>> {
+    let mut area: Maps = HashMap::new();
+    let mut errors = vec![];
+
+    let map_list = get_path("assets/maps/", ".json").map_err(|err| vec![err])?;
+    for map in map_list {
+        let file = map.to_string_lossy();
+        let json_string = match read_to_string(&map) {
+            Ok(json_string) => json_string,
+            Err(err) => {
+                errors.push(MapLoadError::new(MapErrorKind::Io(err.to_string())).in_file(&file));
+                continue;
+            }
+        };
+        match Area::from(&json_string, &file) {
+            Ok((name, map_content)) => {
+                area.insert(name, map_content);
+            }
+            Err(errs) => errors.extend(errs),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(area),
+        false => Err(errors),
+    }
+}
+
+/// Every file in dir with file_type in its name
+pub fn get_path(dir: &str, file_type: &str) -> Result<Vec<PathBuf>, MapLoadError> {
+    let io_err =
+        |err: std::io::Error| MapLoadError::new(MapErrorKind::Io(err.to_string())).in_file(dir);
+    let maps = read_dir(dir).map_err(io_err)?;
 
     let mut return_vec: Vec<PathBuf> = vec![];
     for map in maps {
-        let to_add: PathBuf = map.unwrap().path();
-        if !to_add.to_str().unwrap().contains(file_type) {
+        let to_add: PathBuf = map.map_err(io_err)?.path();
+        if !to_add.to_string_lossy().contains(file_type) {
             continue;
         }
         return_vec.push(to_add)
     }
-    return_vec
+    Ok(return_vec)
 }
 
 pub fn make_anim(name: &str, row: u32, frames: u32, fps: u32) -> Animation {
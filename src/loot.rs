@@ -0,0 +1,197 @@
+use macroquad::prelude::*;
+use macroquad::rand::rand;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::fs::read_to_string;
+use std::rc::Rc;
+
+use crate::logic::*;
+use crate::map::{MapErrorKind, MapLoadError};
+use crate::ui::items::{Item, ItemEntity, ItemRegistry};
+
+/// Every loot table in assets/loot, by file name
+pub type LootTables = HashMap<Rc<str>, LootTable>;
+
+/// What a monster or chest drops. Everything in guaranteed always drops, then one of entries (or
+/// nothing) is picked by weight, rolls times over
+#[derive(Clone, Debug)]
+pub struct LootTable {
+    pub guaranteed: Vec<LootEntry>,
+    pub entries: Vec<LootEntry>,
+    pub rolls: u32,
+    // How likely a roll is to drop nothing, weighed against the entries
+    pub nothing: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LootEntry {
+    pub item: Rc<str>,
+    pub weight: u32,
+    // Both ends included
+    pub min: u8,
+    pub max: u8,
+}
+
+impl LootEntry {
+    fn from_json(json: &Value) -> Result<Self, MapErrorKind> {
+        // Whether the item exists is checked against the registry later, see unknown_items
+        let item = json["item"].as_str().ok_or(MapErrorKind::Field("item"))?;
+        // Either a fixed count or [min, max]
+        let (min, max) = match &json["count"] {
+            Value::Null => (1, 1),
+            Value::Number(count) => {
+                let count = count.as_u64().ok_or(MapErrorKind::Field("count"))?;
+                (count, count)
+            }
+            Value::Array(range) => match range.as_slice() {
+                [min, max] => (
+                    min.as_u64().ok_or(MapErrorKind::Field("count"))?,
+                    max.as_u64().ok_or(MapErrorKind::Field("count"))?,
+                ),
+                _ => return Err(MapErrorKind::Field("count")),
+            },
+            _ => return Err(MapErrorKind::Field("count")),
+        };
+        if min > max || max > u8::MAX as u64 {
+            return Err(MapErrorKind::Field("count"));
+        }
+        let weight = json["weight"].as_u64().unwrap_or(1);
+        Ok(Self {
+            item: item.into(),
+            weight: u32::try_from(weight).map_err(|_| MapErrorKind::Field("weight"))?,
+            min: min as u8,
+            max: max as u8,
+        })
+    }
+
+    fn roll(&self, item_defs: &ItemRegistry) -> Option<Item> {
+        // In u32, since 0..=255 has one more value than fits in a u8
+        let range = self.max as u32 - self.min as u32 + 1;
+        let count = self.min + (rand() % range) as u8;
+        if count == 0 {
+            return None;
+        }
+        Item::from_key(item_defs, &self.item, count)
+    }
+}
+
+impl LootTable {
+    pub fn from_json(json: &Value) -> Result<Self, MapErrorKind> {
+        let read_entries = |field: &'static str| -> Result<Vec<LootEntry>, MapErrorKind> {
+            match &json[field] {
+                Value::Null => Ok(vec![]),
+                Value::Array(entries) => entries.iter().map(LootEntry::from_json).collect(),
+                _ => Err(MapErrorKind::Field(field)),
+            }
+        };
+        let nothing = json["nothing"].as_u64().unwrap_or(0);
+        let table = Self {
+            guaranteed: read_entries("guaranteed")?,
+            entries: read_entries("entries")?,
+            rolls: json["rolls"].as_u64().unwrap_or(1) as u32,
+            nothing: u32::try_from(nothing).map_err(|_| MapErrorKind::Field("nothing"))?,
+        };
+        // Every weight has to add up without overflowing, see roll
+        if table.total_weight().is_none() {
+            return Err(MapErrorKind::Field("weight"));
+        }
+        Ok(table)
+    }
+
+    fn total_weight(&self) -> Option<u32> {
+        let mut weights = self.entries.iter().map(|entry| entry.weight);
+        weights.try_fold(self.nothing, |total, weight| total.checked_add(weight))
+    }
+
+    pub fn roll(&self, item_defs: &ItemRegistry) -> Vec<Item> {
+        let mut items: Vec<Item> = self
+            .guaranteed
+            .iter()
+            .filter_map(|entry| entry.roll(item_defs))
+            .collect();
+
+        let total = match self.total_weight() {
+            Some(total) if total > 0 => total,
+            _ => return items,
+        };
+        for _ in 0..self.rolls {
+            let mut pick = rand() % total;
+            for entry in &self.entries {
+                if pick < entry.weight {
+                    items.extend(entry.roll(item_defs));
+                    break;
+                }
+                pick -= entry.weight;
+            }
+            // Falling through means it landed on nothing
+        }
+        items
+    }
+}
+
+/// Lays items out in a ring around center so they don't all end up in one pile
+pub fn scatter(items: Vec<Item>, center: Vec2) -> Vec<ItemEntity> {
+    let count = items.len();
+    let start = rand() as f32 / u32::MAX as f32 * TAU;
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let offset = match count {
+                1 => Vec2::ZERO,
+                _ => Vec2::from_angle(start + index as f32 * TAU / count as f32) * TILE / 2.,
+            };
+            let mut entity = ItemEntity::new(item, center + offset);
+            // ItemEntity::new takes the top left corner
+            entity.hitbox = entity.hitbox.offset(-entity.hitbox.size() / 2.);
+            entity
+        })
+        .collect()
+}
+
+/// Reads every loot table, reporting all the broken ones at once
+pub fn load_loot_tables() -> Result<LootTables, Vec<MapLoadError>> {
+    let mut tables = LootTables::new();
+    let mut errors = vec![];
+
+    for path in get_path("assets/loot/", ".json").map_err(|err| vec![err])? {
+        let file = path.to_string_lossy();
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let result = read_to_string(&path)
+            .map_err(|err| MapErrorKind::Io(err.to_string()))
+            .and_then(|json| {
+                serde_json::from_str(&json).map_err(|err| MapErrorKind::Json(err.to_string()))
+            })
+            .and_then(|json: Value| LootTable::from_json(&json));
+        match result {
+            Ok(table) => {
+                tables.insert(name.into(), table);
+            }
+            Err(kind) => errors.push(MapLoadError::new(kind).in_file(&file)),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(tables),
+        false => Err(errors),
+    }
+}
+
+/// Every item named by a loot table that isn't in the registry
+pub fn unknown_items(tables: &LootTables, item_defs: &ItemRegistry) -> Vec<MapLoadError> {
+    let mut errors = vec![];
+    for (name, table) in tables {
+        for entry in table.guaranteed.iter().chain(&table.entries) {
+            if item_defs.contains_key(&entry.item) {
+                continue;
+            }
+            let kind = MapErrorKind::UnknownItem(entry.item.to_string());
+            errors.push(MapLoadError::new(kind).in_file(&format!("assets/loot/{name}.json")));
+        }
+    }
+    errors
+}
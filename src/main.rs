@@ -1,16 +1,9 @@
-use logic::*;
+use game::input::InputFrame;
+use game::logic::*;
+use game::map::MapLoadError;
+use game::timestep::FixedTimestep;
 use macroquad::prelude::*;
-use std::{collections::HashMap, path::PathBuf, rc::Rc};
-
-mod camera;
-mod interactables;
-mod logic;
-mod map;
-mod monsters;
-mod npc;
-mod player;
-mod ui;
-mod weapons;
+use std::{collections::HashMap, path::Path, rc::Rc};
 
 fn window_conf() -> Conf {
     Conf {
@@ -24,10 +17,26 @@ fn window_conf() -> Conf {
 #[macroquad::main(window_conf)]
 async fn main() {
     let font = load_font().await;
-    let textures = load_textures().await;
-    let mut game_state = Game::new(textures, font);
+    let textures = match load_textures().await {
+        Ok(textures) => textures,
+        Err(err) => {
+            show_map_errors(&[err], &font).await;
+            return;
+        }
+    };
+    let mut game_state = match Game::new(textures, Some(font.clone())) {
+        Ok(game) => game,
+        Err(errors) => {
+            show_map_errors(&errors, &font).await;
+            return;
+        }
+    };
+    load_tileset_textures(&mut game_state).await;
+    let mut timestep = FixedTimestep::new();
     loop {
-        game_state.tick();
+        let input = InputFrame::capture(game_state.get_mouse_pos());
+        timestep.advance(&mut game_state, input);
+        game_state.prepare_frame(timestep.alpha(), get_frame_time());
         game_state.draw();
         next_frame().await;
 
@@ -37,28 +46,69 @@ async fn main() {
     }
 }
 
-async fn load_textures() -> HashMap<Rc<str>, Texture2D> {
+// Lists everything wrong with the maps until escape is pressed
+async fn show_map_errors(errors: &[MapLoadError], font: &Font) {
+    for err in errors {
+        error!("{err}");
+    }
+    let params = TextParams {
+        font: Some(font),
+        font_size: 24,
+        color: WHITE,
+        ..Default::default()
+    };
+    while !is_key_pressed(KeyCode::Escape) {
+        clear_background(BLACK);
+        let mut y = 40.;
+        draw_text_ex("Some maps failed to load:", 20., y, params.clone());
+        for err in errors {
+            y += 30.;
+            draw_text_ex(&err.to_string(), 20., y, params.clone());
+        }
+        next_frame().await;
+    }
+}
+
+async fn load_textures() -> Result<HashMap<Rc<str>, Texture2D>, MapLoadError> {
     let mut textures: HashMap<Rc<str>, Texture2D> = HashMap::new();
-    let paths = get_path("res/", ".png");
+    let paths = get_path("res/", ".png")?;
     for path in paths {
         let texture = load_texture(path.to_str().unwrap()).await.unwrap();
         texture.set_filter(FilterMode::Nearest);
         let name = to_name(&path);
         textures.insert(name, texture);
     }
-    textures
+    Ok(textures)
+}
+
+// Tilesets can point at images outside of res/, those get loaded here under the same name
+async fn load_tileset_textures(game: &mut Game) {
+    let tilesets: Vec<_> = game.maps.values().flat_map(|map| &map.tilesets).collect();
+    for tileset in tilesets {
+        if game.textures.contains_key(&tileset.texture) {
+            continue;
+        }
+        let path = tileset.image.to_string_lossy();
+        match load_texture(&path).await {
+            Ok(texture) => {
+                texture.set_filter(FilterMode::Nearest);
+                game.textures.insert(tileset.texture.clone(), texture);
+            }
+            Err(err) => error!("could not load tileset image {path}: {err}"),
+        }
+    }
 }
 
 async fn load_font() -> Font {
     load_ttf_font("assets/font/font.ttf").await.unwrap()
 }
 
-fn to_name(path: &PathBuf) -> Rc<str> {
+fn to_name(path: &Path) -> Rc<str> {
     path.file_name()
         .unwrap()
         .to_str()
         .unwrap()
-        .split_once(".")
+        .split_once('.')
         .unwrap()
         .0
         .into()
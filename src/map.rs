@@ -1,41 +1,70 @@
 use macroquad::prelude::*;
 use serde_json::Value;
+use std::path::Path;
 use std::rc::Rc;
 
 use crate::camera::TERRAIN_TILE_SIZE;
-use crate::interactables::{Chest, Door, Interactables};
+use crate::interactables::{Chest, ChestContent, Door, Interactables};
 use crate::logic::*;
+use crate::loot::{scatter, LootTables};
+use crate::monsters::definition::MonsterDefs;
 use crate::monsters::*;
-use crate::npc::NPC;
-use crate::player::PIXEL;
+use crate::navigation::NavGrid;
+use crate::npc::{load_dialog, Movement, Route, NPC};
+use crate::player::{Collidable, Player, PIXEL};
+use crate::spatial::{SpatialGrid, Walls};
+use crate::tileset::{Tile, Tileset};
 use crate::ui::items::*;
+use boss::{BossArena, Fight};
 use spawner::*;
 
 pub const RATIO: f32 = TILE / TERRAIN_TILE_SIZE;
 const PROJ_SPEED: f32 = 2000.;
+// How much of the overlap between two monsters is undone every tick
+const SEPARATION: f32 = 0.5;
 pub type Monster = Box<dyn Entity>;
 pub type Interactable = Box<dyn Interactables>;
 
 pub struct Area {
     pub enemies: Vec<Monster>,
-    pub walls: Vec<Rect>,
+    pub walls: Walls,
+    pub nav: NavGrid,
     pub gates: Vec<Gate>,
     pub spawners: Vec<Spawner>,
     pub npcs: Vec<NPC>,
     pub projectiles: Vec<Projectile>,
     pub items: Vec<ItemEntity>,
     pub interactables: Vec<Interactable>,
+    pub bosses: Vec<BossArena>,
     pub draw_mesh: Meshes,
+    pub tilesets: Vec<Tileset>,
 }
 
 pub struct Projectile {
+    pub owner: Faction,
+    sprite: ProjectileSprite,
     pos: Vec2,
+    prev_pos: Vec2,
+    render_pos: Vec2,
     speed: Vec2,
     damage: f32,
     life_time: Timer,
     should_despawn: bool,
 }
 
+/// Who shot a projectile, which is also who it can't hurt
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Faction {
+    Player,
+    Monster,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProjectileSprite {
+    pub texture: Rc<str>,
+    pub source: Rect,
+}
+
 #[derive(Clone)]
 pub struct Gate {
     pub map: Rc<str>,
@@ -43,29 +72,80 @@ pub struct Gate {
     pub location: Vec2,
 }
 
+// Every tile layer in the order Tiled draws them
 pub struct Meshes {
-    pub terrain: Vec<Vec<u16>>,
-    pub decorations: Vec<Vec<u16>>,
+    pub layers: Vec<TileLayer>,
+    // The layer the player, monsters, NPCs and so on are drawn in, sorted together with its tiles
+    pub entity_layer: usize,
+}
+
+// Look up which tileset a cell is from with find_tileset
+pub struct TileLayer {
+    pub name: Rc<str>,
+    pub tiles: Vec<Vec<Tile>>,
 }
 
 impl Meshes {
     fn new() -> Self {
-        let terrain = vec![];
-        let decorations = vec![];
-
         Meshes {
-            terrain,
-            decorations,
+            layers: vec![],
+            entity_layer: 0,
+        }
+    }
+
+    /// The map's size in tiles comes from this one, empty if the map has no Terrain layer
+    pub fn terrain(&self) -> &[Vec<Tile>] {
+        self.layers
+            .iter()
+            .find(|layer| layer.name.eq_ignore_ascii_case("terrain"))
+            .map(|layer| layer.tiles.as_slice())
+            .unwrap_or_default()
+    }
+
+    // Maps from before entity layers were a thing get one right above Terrain, which is where
+    // entities used to be drawn
+    fn add_entity_layer(&mut self, designated: Option<usize>) {
+        self.entity_layer = match designated {
+            Some(index) => index,
+            None => {
+                let terrain = self
+                    .layers
+                    .iter()
+                    .position(|layer| layer.name.eq_ignore_ascii_case("terrain"))
+                    .unwrap_or(0);
+                let entities = TileLayer {
+                    name: "Entities".into(),
+                    tiles: vec![],
+                };
+                self.layers.insert(terrain + 1, entities);
+                terrain + 1
+            }
         }
     }
 }
 
+/// Everything a projectile could hit
+pub struct Targets<'a> {
+    pub monsters: &'a mut [Monster],
+    pub monster_index: &'a SpatialGrid,
+    pub player: &'a mut Player,
+    pub walls: &'a Walls,
+}
+
 impl Projectile {
     pub fn new(pos: Vec2, speed: Vec2) -> Self {
         let speed = vec2(speed.x, -speed.y);
         let pos = vec2(pos.x, pos.y + 6. * PIXEL);
+        let sprite = ProjectileSprite {
+            texture: "player".into(),
+            source: Rect::new(TILE_SIZE * 6., TILE_SIZE, TILE_SIZE, TILE_SIZE),
+        };
         Self {
+            owner: Faction::Player,
+            sprite,
             pos,
+            prev_pos: pos,
+            render_pos: pos,
             life_time: Timer::new(0.8),
             should_despawn: false,
             speed: speed * PROJ_SPEED,
@@ -73,10 +153,44 @@ impl Projectile {
         }
     }
 
-    pub fn draw(&self, texture: &Texture2D) {
-        let center = self.hitbox().center();
+    /// Shot by a monster from center, velocity is in world units per second
+    pub fn hostile(
+        center: Vec2,
+        velocity: Vec2,
+        damage: f32,
+        life_time: f32,
+        sprite: ProjectileSprite,
+    ) -> Self {
+        let mut projectile = Self {
+            owner: Faction::Monster,
+            sprite,
+            pos: center,
+            prev_pos: center,
+            render_pos: center,
+            speed: velocity,
+            damage,
+            life_time: Timer::new(life_time),
+            should_despawn: false,
+        };
+        let size = projectile.hitbox().size();
+        projectile.pos -= size / 2.;
+        projectile.snap_pos();
+        projectile
+    }
+
+    fn snap_pos(&mut self) {
+        self.prev_pos = self.pos;
+        self.render_pos = self.pos;
+    }
+
+    pub fn draw(&self, textures: &Textures) {
+        let texture = match textures.get(&self.sprite.texture) {
+            Some(texture) => texture,
+            None => return,
+        };
+        let center = self.hitbox().center() + self.render_pos - self.pos;
         let dest_size = Some(vec2(TILE, TILE));
-        let source = Some(Rect::new(TILE_SIZE * 6., TILE_SIZE, TILE_SIZE, TILE_SIZE));
+        let source = Some(self.sprite.source);
         let rotation = self.speed.angle_between(vec2(1., 0.));
         let params = DrawTextureParams {
             dest_size,
@@ -94,12 +208,22 @@ impl Projectile {
         )
     }
 
-    pub fn tick(&mut self, monsters: &mut Vec<Monster>) {
-        self.new_pos();
-        self.life_time.tick();
-        let hitbox = self.hitbox();
+    pub fn tick(&mut self, targets: &mut Targets, dt: f32) {
+        self.prev_pos = self.pos;
+        self.new_pos(dt);
+        self.life_time.tick(dt);
+
+        match self.owner {
+            Faction::Player => self.hit_monsters(targets.monsters, targets.monster_index),
+            Faction::Monster => self.hit_player(targets.player, targets.walls),
+        }
+    }
 
-        for monster in monsters.iter_mut() {
+    /// monster_index has to be up to date with monsters
+    fn hit_monsters(&mut self, monsters: &mut [Monster], monster_index: &SpatialGrid) {
+        let hitbox = self.hitbox();
+        for index in monster_index.query(hitbox) {
+            let monster = &mut monsters[index];
             if hitbox.overlaps(&monster.hitbox()) {
                 let props = monster.get_mut_props();
                 props.health -= self.damage;
@@ -109,8 +233,29 @@ impl Projectile {
         }
     }
 
-    fn new_pos(&mut self) {
-        let dt = get_frame_time();
+    // Unlike the player's, these stop at walls so there is always somewhere to hide
+    fn hit_player(&mut self, player: &mut Player, walls: &Walls) {
+        let hitbox = self.hitbox();
+        if walls.near(hitbox).any(|wall| wall.overlaps(&hitbox)) {
+            self.should_despawn = true;
+            return;
+        }
+        if !hitbox.overlaps(&player.hitbox()) {
+            return;
+        }
+        self.should_despawn = true;
+        if player.hurt(self.damage) {
+            player
+                .props
+                .knockback(self.speed.normalize_or_zero() * KNOCKBACK / 2.);
+        }
+    }
+
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.render_pos = self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    fn new_pos(&mut self, dt: f32) {
         self.pos.x += self.speed.x * dt;
         self.pos.y += self.speed.y * dt;
     }
@@ -120,88 +265,408 @@ impl Projectile {
     }
 }
 
-impl Area {
-    pub fn from(json_string: &str) -> (Rc<str>, Self) {
-        // Now I could go ahead and handle all of these potential errors like a good developer but
-        // instead, I chose to ignore it. Now that the code is a tangled mess, it is quite
-        // difficult to handle all of the errors
-        let parsed: Value = serde_json::from_str(json_string).unwrap();
-        let name = parsed["class"].as_str().unwrap();
+#[derive(Debug, Clone)]
+pub struct MapLoadError {
+    pub file: Rc<str>,
+    pub layer: Option<Rc<str>>,
+    // The Tiled object id
+    pub object: Option<u64>,
+    pub kind: MapErrorKind,
+}
 
+#[derive(Debug, Clone)]
+pub enum MapErrorKind {
+    Io(String),
+    Json(String),
+    // A field that is either missing or of the wrong type
+    Field(&'static str),
+    MissingLayer(&'static str),
+    NoCommand,
+    BadCommand(String),
+    Dialog(String, String),
+    UnknownInteractable(String),
+    Tileset(String),
+    UnknownTarget(u64),
+    UnknownPath(u64),
+    // The rest are only found by validate-maps, since they need every map to be loaded first
+    UnknownMap(String),
+    OutOfBounds(String, Vec2),
+    InsideWall(String, Vec2),
+    UnknownItem(String),
+    UnknownMob(String),
+    UnknownLoot(String),
+    UnknownQuest(String),
+    UnknownNpc(String),
+    UnknownSprite(String),
+    // Sold in a shop but worth nothing
+    FreeItem(String),
+    BadWaves(String),
+}
+
+impl MapLoadError {
+    pub fn new(kind: MapErrorKind) -> Self {
+        Self {
+            file: "".into(),
+            layer: None,
+            object: None,
+            kind,
+        }
+    }
+
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file = file.into();
+        self
+    }
+
+    pub fn in_layer(mut self, layer: &str) -> Self {
+        self.layer = Some(layer.into());
+        self
+    }
+
+    pub fn in_object(mut self, id: Option<u64>) -> Self {
+        self.object = id;
+        self
+    }
+}
+
+impl std::fmt::Display for MapLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(layer) = &self.layer {
+            write!(f, ", layer {layer}")?;
+        }
+        if let Some(id) = self.object {
+            write!(f, ", object {id}")?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::fmt::Display for MapErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapErrorKind::Io(err) => write!(f, "could not read file: {err}"),
+            MapErrorKind::Json(err) => write!(f, "invalid JSON: {err}"),
+            MapErrorKind::Field(field) => {
+                write!(f, "field \"{field}\" is missing or of the wrong type")
+            }
+            MapErrorKind::MissingLayer(layer) => write!(f, "map has no {layer} layer"),
+            MapErrorKind::NoCommand => write!(f, "no \"to\" property"),
+            MapErrorKind::BadCommand(command) => write!(
+                f,
+                "\"to\" is \"{command}\" but should look like \"<map> <x> <y>\""
+            ),
+            MapErrorKind::Dialog(path, err) => write!(f, "dialog {path}: {err}"),
+            MapErrorKind::UnknownInteractable(kind) => {
+                write!(f, "unrecognised interactable type \"{kind}\"")
+            }
+            MapErrorKind::UnknownMap(map) => write!(f, "no map has the class \"{map}\""),
+            MapErrorKind::OutOfBounds(map, pos) => write!(
+                f,
+                "destination {} {} is outside of {map}",
+                pos.x / TILE,
+                pos.y / TILE
+            ),
+            MapErrorKind::InsideWall(map, pos) => write!(
+                f,
+                "destination {} {} in {map} is inside a wall",
+                pos.x / TILE,
+                pos.y / TILE
+            ),
+            MapErrorKind::UnknownItem(item) => write!(f, "unrecognised item \"{item}\""),
+            MapErrorKind::UnknownMob(mob) => write!(f, "unrecognised mob type \"{mob}\""),
+            MapErrorKind::UnknownLoot(table) => write!(f, "no loot table called \"{table}\""),
+            MapErrorKind::UnknownQuest(quest) => write!(f, "no quest called \"{quest}\""),
+            MapErrorKind::UnknownNpc(npc) => write!(f, "no NPC called \"{npc}\" in any map"),
+            MapErrorKind::UnknownSprite(sprite) => write!(f, "no texture res/{sprite}.png"),
+            MapErrorKind::FreeItem(item) => {
+                write!(f, "\"{item}\" is worth nothing so can't be sold")
+            }
+            MapErrorKind::BadWaves(waves) => write!(
+                f,
+                "\"waves\" is \"{waves}\" but should look like \"slime 3, mushroom 1; spitter 2\""
+            ),
+            MapErrorKind::Tileset(err) => write!(f, "bad tileset: {err}"),
+            MapErrorKind::UnknownTarget(id) => {
+                write!(f, "object {id} is not a door or chest in this map")
+            }
+            MapErrorKind::UnknownPath(id) => {
+                write!(f, "object {id} is not in the Paths layer of this map")
+            }
+        }
+    }
+}
+
+type LayerResult<T> = Result<T, Vec<MapLoadError>>;
+
+impl Area {
+    /// Collects every problem in the map instead of stopping at the first one. file is only used
+    /// to say where the errors came from
+    pub fn from(json_string: &str, file: &str) -> Result<(Rc<str>, Self), Vec<MapLoadError>> {
+        let in_file = |errors: Vec<MapLoadError>| -> Vec<MapLoadError> {
+            errors.into_iter().map(|err| err.in_file(file)).collect()
+        };
+        let parsed: Value = serde_json::from_str(json_string)
+            .map_err(|err| in_file(layer_err(MapErrorKind::Json(err.to_string()))))?;
+        let name = parsed["class"]
+            .as_str()
+            .ok_or(MapErrorKind::Field("class"))
+            .map_err(|kind| in_file(layer_err(kind)))?;
+        let layers = parsed["layers"]
+            .as_array()
+            .ok_or(MapErrorKind::Field("layers"))
+            .map_err(|kind| in_file(layer_err(kind)))?;
+
+        let mut errors = vec![];
+        // Tilesets and their images are relative to the map file
+        let map_dir = Path::new(file).parent().unwrap_or(Path::new(""));
+        let mut tilesets = vec![];
+        for entry in parsed["tilesets"].as_array().unwrap_or(&vec![]) {
+            match Tileset::from_map(entry, map_dir) {
+                Ok(tileset) => tilesets.push(tileset),
+                Err(err) => errors.extend(layer_err(MapErrorKind::Tileset(err))),
+            }
+        }
         let mut draw_mesh = Meshes::new();
         let mut walls = vec![];
         let mut spawners = vec![];
         let mut gates = vec![];
         let mut npcs = vec![];
+        let mut paths = vec![];
         let mut interactables = vec![];
-
-        for layer in parsed["layers"].as_array().unwrap() {
-            match layer["name"].as_str().unwrap().to_lowercase().as_str() {
-                "terrain" => {
-                    draw_mesh.terrain = make_render_mesh(layer).unwrap();
-                }
-                "walls" => {
-                    walls = make_walls(layer);
-                }
-                "decorations" => {
-                    draw_mesh.decorations = make_render_mesh(layer).unwrap();
+        let mut bosses = vec![];
+        let mut entity_layer = None;
+
+        for layer in layers {
+            let layer_name = match layer["name"].as_str() {
+                Some(name) => name,
+                None => {
+                    errors.extend(layer_err(MapErrorKind::Field("name")));
+                    continue;
                 }
-                "spawners" => {
-                    spawners = make_spawners(layer).unwrap();
+            };
+            if layer_name.eq_ignore_ascii_case("entities") || get_bool_property(layer, "entities") {
+                entity_layer = Some(draw_mesh.layers.len());
+                // Object layers can say where entities go too, they just have no tiles of their own
+                if layer["type"].as_str() != Some("tilelayer") {
+                    let tiles = vec![];
+                    draw_mesh.layers.push(TileLayer {
+                        name: layer_name.into(),
+                        tiles,
+                    });
                 }
-                "gates" => {
-                    gates = make_gates(layer).unwrap();
-                }
-                "npcs" => {
-                    npcs = make_npcs(layer).unwrap();
+            }
+            if layer["type"].as_str() == Some("tilelayer") {
+                match make_render_mesh(layer) {
+                    Ok(tiles) => draw_mesh.layers.push(TileLayer {
+                        name: layer_name.into(),
+                        tiles,
+                    }),
+                    Err(errs) => {
+                        errors.extend(errs.into_iter().map(|err| err.in_layer(layer_name)))
+                    }
                 }
+                continue;
+            }
+            let result = match layer_name.to_lowercase().as_str() {
+                "walls" => parse_objects(layer, make_wall).map(|result| walls = result),
+                "spawners" => parse_objects(layer, make_spawner).map(|result| spawners = result),
+                "gates" => parse_objects(layer, make_gate).map(|result| gates = result),
+                "npcs" => parse_objects(layer, make_npc).map(|result| npcs = result),
+                "paths" => parse_objects(layer, make_path).map(|result| paths = result),
                 "interactables" => {
-                    interactables = parse_interactable(layer);
+                    parse_objects(layer, make_interactable).map(|result| interactables = result)
                 }
-                _ => (),
+                "bosses" => parse_objects(layer, make_boss).map(|result| bosses = result),
+                _ => Ok(()),
+            };
+            if let Err(errs) = result {
+                errors.extend(errs.into_iter().map(|err| err.in_layer(layer_name)));
             }
         }
 
-        for npc in &npcs {
-            walls.push(npc.hitbox)
+        if draw_mesh.terrain().is_empty() {
+            errors.extend(layer_err(MapErrorKind::MissingLayer("Terrain")));
+        }
+        draw_mesh.add_entity_layer(entity_layer);
+        // Whatever bosses and waves unlock has to be there
+        let boss_targets = bosses
+            .iter()
+            .map(|arena| ("Bosses", arena.id, arena.reward));
+        let wave_targets = spawners.iter().filter_map(|spawner| match &spawner.mode {
+            SpawnMode::Waves(waves) => Some(("Spawners", waves.id, waves.unlocks)),
+            SpawnMode::Timer { .. } => None,
+        });
+        for (layer, id, target) in boss_targets.chain(wave_targets) {
+            let target = match target {
+                Some(target) => target,
+                None => continue,
+            };
+            if !interactables.iter().any(|chest| chest.id() == target) {
+                let err = MapLoadError::new(MapErrorKind::UnknownTarget(target));
+                errors.push(err.in_layer(layer).in_object(Some(id)));
+            }
+        }
+        // NPCs only have the id of their path
+        for npc in npcs.iter_mut() {
+            let target = match npc.path {
+                Some(target) => target,
+                None => continue,
+            };
+            match paths.iter().find(|route: &&Route| route.id == target) {
+                Some(route) => npc.movement = route.movement.clone(),
+                None => {
+                    let err = MapLoadError::new(MapErrorKind::UnknownPath(target));
+                    errors.push(err.in_layer("NPCs").in_object(Some(npc.id)));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(in_file(errors));
         }
+
         for chest in &interactables {
             walls.push(chest.hitbox())
         }
+        let terrain = draw_mesh.terrain();
+        let nav = NavGrid::new(&walls, terrain[0].len(), terrain.len());
+        let walls = Walls::new(walls);
+
+        let mut area = Area {
+            enemies: vec![],
+            projectiles: vec![],
+            items: vec![],
+            spawners,
+            draw_mesh,
+            tilesets,
+            gates,
+            walls,
+            nav,
+            npcs,
+            interactables,
+            bosses,
+        };
+        // Reward chests and the like start out locked
+        area.update_locks();
+        Ok((name.into(), area))
+    }
 
-        (
-            name.into(),
-            Area {
-                enemies: vec![],
-                projectiles: vec![],
-                items: vec![],
-                spawners,
-                draw_mesh,
-                gates,
-                walls,
-                npcs,
-                interactables,
-            },
-        )
+    /// Which monster is where, this goes out of date as soon as any of them move
+    pub fn index_monsters(&self) -> SpatialGrid {
+        SpatialGrid::from_rects(self.enemies.iter().map(|monster| monster.hitbox()))
+    }
+
+    /// Nudges overlapping monsters apart so packs spread out around the player instead of piling
+    /// onto one spot. Only part of the overlap goes away each tick, which keeps it soft. Also
+    /// keeps them out of NPCs
+    pub fn separate_monsters(&mut self) {
+        let index = self.index_monsters();
+        let hitboxes: Vec<Rect> = self
+            .enemies
+            .iter()
+            .map(|monster| monster.hitbox())
+            .collect();
+        let mut pushes = vec![Vec2::ZERO; hitboxes.len()];
+
+        for (i, hitbox) in hitboxes.iter().enumerate() {
+            for j in index.query(*hitbox) {
+                if j <= i {
+                    continue;
+                }
+                let overlap = match hitbox.intersect(hitboxes[j]) {
+                    Some(overlap) => overlap,
+                    None => continue,
+                };
+                let mut away = hitbox.center() - hitboxes[j].center();
+                // Right on top of each other, any direction will do
+                if away == Vec2::ZERO {
+                    away = vec2(1., 0.);
+                }
+                let push = away.normalize() * overlap.w.min(overlap.h) * SEPARATION / 2.;
+                pushes[i] += push;
+                pushes[j] -= push;
+            }
+        }
+
+        let npcs: Vec<Rect> = self.npcs.iter().map(|npc| npc.hitbox).collect();
+        for (monster, push) in self.enemies.iter_mut().zip(pushes) {
+            *monster.mut_pos() += push;
+            monster.collide(&npcs);
+            monster.wall_collsion(&self.walls);
+        }
+    }
+
+    /// NPCs walk around the player, each other and the walls, but never push anything
+    pub fn tick_npcs(&mut self, player: Rect, dt: f32) {
+        for index in 0..self.npcs.len() {
+            let others = self
+                .npcs
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index);
+            let mut obstacles: Vec<Rect> = others.map(|(_, npc)| npc.hitbox).collect();
+            obstacles.push(player);
+            self.npcs[index].tick(&obstacles, &self.walls, dt);
+        }
+    }
+
+    pub fn tick_spawners(&mut self, player: Rect, defs: &MonsterDefs, dt: f32) {
+        let mut cleared = false;
+        for spawner in self.spawners.iter_mut() {
+            cleared |= spawner.tick(&mut self.enemies, defs, player, dt);
+        }
+        if cleared {
+            self.update_locks()
+        }
     }
 
-    pub fn clean_up(&mut self) {
+    /// Doors and chests are locked during a boss fight. Boss rewards stay locked until the boss is
+    /// beaten, and whatever a wave spawner unlocks until every wave is dead
+    pub fn update_locks(&mut self) {
+        let sealed = self.is_sealed();
+        for interactable in self.interactables.iter_mut() {
+            let id = interactable.id();
+            let reward = self
+                .bosses
+                .iter()
+                .any(|arena| arena.reward == Some(id) && arena.fight != Fight::Won);
+            let waves = self.spawners.iter().any(|spawner| match &spawner.mode {
+                SpawnMode::Waves(waves) => {
+                    waves.unlocks == Some(id) && waves.state != WaveState::Cleared
+                }
+                SpawnMode::Timer { .. } => false,
+            });
+            interactable.set_locked(sealed || reward || waves);
+        }
+    }
+
+    /// Returns the name of every monster that died, for the quests
+    pub fn clean_up(&mut self, loot_tables: &LootTables, item_defs: &ItemRegistry) -> Vec<Rc<str>> {
         let projectiles = &mut self.projectiles;
         let mobs = &mut self.enemies;
+        let mut dead = vec![];
         // Spawn loot for every dying mob
         for mob in mobs.iter() {
             if !mob.get_props().should_despawn {
                 continue;
             }
-            let loot = mob.loot();
-            if let Some(loot) = loot {
-                self.items.push(ItemEntity::new(loot, mob.pos()))
+            dead.push(mob.name().into());
+            let table = match mob.loot() {
+                Some(table) => table,
+                None => continue,
+            };
+            match loot_tables.get(table) {
+                Some(table) => self
+                    .items
+                    .extend(scatter(table.roll(item_defs), mob.hitbox().center())),
+                None => warn!("unknown loot table {table}"),
             }
         }
         let items = &mut self.items;
         mobs.retain(|mob| !mob.get_props().should_despawn);
         projectiles.retain(|proj| !proj.should_despawn && !proj.life_time.is_done());
         items.retain(|item| !item.should_delete);
+        dead
     }
 }
 impl Gate {
@@ -224,71 +689,90 @@ impl Gate {
     }
 }
 
-// I dont really need these to be of type Option but doing so will alow me to use the ? operator,
-// which is shorter than just writing out .unwrap()
-fn make_render_mesh(objects: &Value) -> Option<Vec<Vec<u16>>> {
+fn layer_err(kind: MapErrorKind) -> Vec<MapLoadError> {
+    vec![MapLoadError::new(kind)]
+}
+
+/// Runs parse on every object in an object layer, keeping track of which object failed
+pub fn parse_objects<T, F>(layer: &Value, parse: F) -> LayerResult<Vec<T>>
+where
+    F: Fn(&Value) -> Result<T, MapErrorKind>,
+{
+    let objects = layer["objects"]
+        .as_array()
+        .ok_or(layer_err(MapErrorKind::Field("objects")))?;
+
+    let mut parsed = vec![];
+    let mut errors = vec![];
+    for object in objects {
+        match parse(object) {
+            Ok(result) => parsed.push(result),
+            Err(kind) => errors.push(MapLoadError::new(kind).in_object(object["id"].as_u64())),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(parsed),
+        false => Err(errors),
+    }
+}
+
+pub fn make_render_mesh(layer: &Value) -> LayerResult<Vec<Vec<Tile>>> {
     // Ah yes, functional programming
-    let parsed = objects["data"].as_array()?;
-    let lenght = objects["width"].as_u64()? as usize;
+    let parsed = layer["data"]
+        .as_array()
+        .ok_or(layer_err(MapErrorKind::Field("data")))?;
+    let lenght = match layer["width"].as_u64() {
+        Some(width) if width > 0 => width as usize,
+        _ => return Err(layer_err(MapErrorKind::Field("width"))),
+    };
 
-    let temp: Vec<u16> = parsed
+    // The flip flags make ids too big for anything smaller than a u32
+    let temp: Option<Vec<Tile>> = parsed
         .iter()
-        .map(|elem| elem.as_i64().unwrap() as u16)
+        .map(|elem| elem.as_u64().map(|id| Tile::from_raw(id as u32)))
         .collect();
+    let temp = temp.ok_or(layer_err(MapErrorKind::Field("data")))?;
 
     let return_vec = temp.chunks(lenght).map(|elem| elem.into()).collect();
 
-    Some(return_vec)
+    Ok(return_vec)
 }
 
-fn make_walls(objects: &Value) -> Vec<Rect> {
-    let raw_data;
-    // Some thing is telling me I can do better than this
-    if let Some(value) = objects.get("objects") {
-        if let Some(arr) = value.as_array() {
-            raw_data = arr
-        } else {
-            error!("make_walls [ERROR] field object is not of type array");
-            return vec![];
-        }
-    } else {
-        error!("make_walls [ERROR] field objects does not exist");
-        return vec![];
-    }
-
-    let mut walls: Vec<Rect> = vec![];
-    for wall in raw_data {
-        let mut wall_cons = Rect::new(0., 0., 0., 0.);
-        wall_cons.x = get_pos(wall, "x", "make_walls") * RATIO;
-        wall_cons.y = get_pos(wall, "y", "make_walls") * RATIO;
-        wall_cons.w = get_pos(wall, "width", "make_walls") * RATIO;
-        wall_cons.h = get_pos(wall, "height", "make_walls") * RATIO;
-
-        walls.push(wall_cons);
-    }
-    walls
+fn make_wall(wall: &Value) -> Result<Rect, MapErrorKind> {
+    get_rect(wall)
 }
 
-fn make_spawners(objects: &Value) -> Option<Vec<Spawner>> {
-    let mut spawners: Vec<Spawner> = vec![];
-    let data = objects["objects"].as_array()?;
-    for spawner in data {
-        let x = spawner["x"].as_f64()? as f32;
-        let y = spawner["y"].as_f64()? as f32;
-        let (cooldown, spawn_radius, kind, max_mob) = get_props(spawner)?;
-
-        let spawner = Spawner::new(
-            kind,
-            spawn_radius,
-            max_mob,
-            cooldown,
-            vec2(x * RATIO, y * RATIO),
-        );
-
-        spawners.push(spawner)
+fn make_spawner(spawner: &Value) -> Result<Spawner, MapErrorKind> {
+    let x = get_f32(spawner, "x")?;
+    let y = get_f32(spawner, "y")?;
+    let (cooldown, spawn_radius, kind, max_mob) = get_props(spawner)?;
+
+    // Wave spawners are rectangles that start once the player walks in, or points that start once
+    // the player gets within spawn_radius
+    if let Some(waves) = get_property(spawner, "waves") {
+        let waves = parse_waves(waves)?;
+        let id = spawner["id"].as_u64().unwrap_or_default();
+        let mut region = get_rect(spawner)?;
+        if region.w == 0. || region.h == 0. {
+            region = Rect::new(
+                region.x - spawn_radius,
+                region.y - spawn_radius,
+                spawn_radius * 2.,
+                spawn_radius * 2.,
+            );
+        }
+        let unlocks = get_object_property(spawner, "unlocks");
+        let waves = Waves::new(id, waves, region, unlocks);
+        return Ok(Spawner::waves(waves, spawn_radius, region.center()));
     }
 
-    Some(spawners)
+    Ok(Spawner::new(
+        kind,
+        spawn_radius,
+        max_mob,
+        cooldown,
+        vec2(x * RATIO, y * RATIO),
+    ))
 }
 
 // f32 cooldown
@@ -296,73 +780,61 @@ fn make_spawners(objects: &Value) -> Option<Vec<Spawner>> {
 // String kind
 // int max_mob
 
-fn get_props(objects: &Value) -> Option<(f32, f32, MobType, u32)> {
-    let props = objects["properties"].as_array()?;
+fn get_props(objects: &Value) -> Result<(f32, f32, Rc<str>, u32), MapErrorKind> {
     // Default values
     let mut cooldown = 30.;
     let mut spawn_radius = 3. * TILE;
-    let mut kind = MobType::Slime;
+    let mut kind: Rc<str> = "slime".into();
     let mut max_mob = 3;
 
+    // Whether the monster exists is up to validate-maps, since monsters are loaded separately
     if let Some(mob) = objects["type"].as_str() {
-        kind = what_kind(mob)
+        if !mob.is_empty() {
+            kind = mob.into()
+        }
     }
+    // A spawner with nothing set just uses the defaults
+    let props = match objects["properties"].as_array() {
+        Some(props) => props,
+        None => return Ok((cooldown, spawn_radius, kind, max_mob)),
+    };
     for prop in props {
-        match prop["name"].as_str()? {
-            "cooldown" => cooldown = prop["value"].as_f64()? as f32,
-            "max_mob" => max_mob = prop["value"].as_f64()? as u32,
-            "spawn_radius" => {
-                spawn_radius = prop["value"].as_f64()? as f32 * TILE;
-            }
-            x => warn!("[WARN] unrecognised field name {}", x),
+        let name = prop["name"].as_str().ok_or(MapErrorKind::Field("name"))?;
+        match name {
+            "cooldown" => cooldown = get_f32(prop, "value")?,
+            "max_mob" => max_mob = get_f32(prop, "value")? as u32,
+            "spawn_radius" => spawn_radius = get_f32(prop, "value")? * TILE,
+            // Only for wave spawners, read in make_spawner
+            "waves" | "unlocks" => (),
+            x => warn!("unrecognised field name {x}"),
         }
     }
 
-    Some((cooldown, spawn_radius, kind, max_mob))
+    Ok((cooldown, spawn_radius, kind, max_mob))
 }
 
-fn what_kind(name: &str) -> MobType {
-    match name {
-        "slime" => MobType::Slime,
-        "mushroom" => MobType::Mushroom,
-        x => {
-            warn!("[WARN] unrecognised mob type {}, falling back to slime", x);
-            MobType::Slime
-        }
-    }
+// The rectangle is the arena, type is which monster the boss is
+fn make_boss(boss: &Value) -> Result<BossArena, MapErrorKind> {
+    let kind = match boss["type"].as_str() {
+        Some(kind) if !kind.is_empty() => kind,
+        _ => return Err(MapErrorKind::Field("type")),
+    };
+    let arena = get_rect(boss)?;
+    let id = boss["id"].as_u64().unwrap_or_default();
+    let reward = get_object_property(boss, "reward");
+    let flag = get_property(boss, "flag");
+    Ok(BossArena::new(id, kind, arena, reward, flag))
 }
 
-fn make_gates(objects: &Value) -> Option<Vec<Gate>> {
-    let mut gates: Vec<Gate> = vec![];
-
-    let props = objects["objects"].as_array()?;
-    for gate in props {
-        let x = get_pos(gate, "x", "make_gates") * RATIO;
-        let y = get_pos(gate, "y", "make_gates") * RATIO;
-        let w = get_pos(gate, "width", "make_gates") * RATIO;
-        let h = get_pos(gate, "height", "make_gates") * RATIO;
-        let hitbox = Rect::new(x, y, w, h);
-
-        let command = match get_command(&gate["properties"]) {
-            Some(command) => command,
-            None => {
-                dbg!(gate);
-                dbg!(objects);
-                panic!("shid")
-            }
-        };
-        let commands: Vec<&str> = command.split_whitespace().collect();
-        let command = commands[0].trim().into();
-        let pos_x = commands[1].trim().parse::<f32>().unwrap() * TILE;
-        let pos_y = commands[2].trim().parse::<f32>().unwrap() * TILE;
+fn make_gate(gate: &Value) -> Result<Gate, MapErrorKind> {
+    let hitbox = get_rect(gate)?;
+    let command = get_command(&gate["properties"]).ok_or(MapErrorKind::NoCommand)?;
+    let (map, location) = parse_command(command)?;
 
-        gates.push(Gate::new(hitbox, vec2(pos_x, pos_y), command))
-    }
-
-    Some(gates)
+    Ok(Gate::new(hitbox, location, map))
 }
 
-fn get_command(objects: &Value) -> Option<&str> {
+pub fn get_command(objects: &Value) -> Option<&str> {
     let commands = objects.as_array()?;
 
     let mut string = "";
@@ -377,140 +849,167 @@ fn get_command(objects: &Value) -> Option<&str> {
     Some(string)
 }
 
-fn make_npcs(objects: &Value) -> Option<Vec<NPC>> {
-    let mut npcs: Vec<NPC> = vec![];
+/// Splits a "to" command like "Village 48 27" into the map and the position in world space
+pub fn parse_command(command: &str) -> Result<(&str, Vec2), MapErrorKind> {
+    let bad_command = || MapErrorKind::BadCommand(command.to_string());
+    let mut commands = command.split_whitespace();
+
+    let map = commands.next().ok_or_else(bad_command)?;
+    let mut coord = || -> Result<f32, MapErrorKind> {
+        let value = commands.next().ok_or_else(bad_command)?;
+        value.parse::<f32>().map_err(|_| bad_command())
+    };
+    let pos_x = coord()? * TILE;
+    let pos_y = coord()? * TILE;
+    if commands.next().is_some() {
+        return Err(bad_command());
+    }
 
-    let list = objects["objects"].as_array()?;
+    Ok((map, vec2(pos_x, pos_y)))
+}
 
-    for item in list {
-        let mut diag_path = "";
-        let name = item["name"].as_str()?;
+fn make_npc(item: &Value) -> Result<NPC, MapErrorKind> {
+    let name = item["name"].as_str().ok_or(MapErrorKind::Field("name"))?;
 
-        let x = get_pos(item, "x", "make_npcs");
-        let y = get_pos(item, "y", "make npcs");
-        let hitbox = Rect::new(x * RATIO, y * RATIO, 100., 50.);
+    let x = get_f32(item, "x")?;
+    let y = get_f32(item, "y")?;
+    let hitbox = Rect::new(x * RATIO, y * RATIO, 100., 50.);
 
-        let props = item["properties"].as_array()?;
+    let diag_path = get_property(item, "dialog").ok_or(MapErrorKind::Field("dialog"))?;
+    let dialogue =
+        load_dialog(diag_path).map_err(|err| MapErrorKind::Dialog(diag_path.to_string(), err))?;
 
-        for prop in props {
-            if prop["name"].as_str()? == "dialog" {
-                diag_path = prop["value"].as_str()?;
-            }
-        }
-        let npc = NPC::new(name, &diag_path, hitbox);
-        npcs.push(npc);
+    let mut npc = NPC::new(name, dialogue, hitbox);
+    npc.id = item["id"].as_u64().unwrap_or_default();
+    npc.path = get_object_property(item, "path");
+    if let Some(sprite) = get_property(item, "sprite") {
+        npc.sprite = sprite.into()
     }
-
-    Some(npcs)
+    // Looks like "slime, mushroom, rusty_sword"
+    if let Some(stock) = get_property(item, "shop") {
+        let stock = stock.split(',').map(str::trim).filter(|id| !id.is_empty());
+        npc.shop = stock.map(|id| id.into()).collect();
+    }
+    Ok(npc)
 }
 
-fn parse_interactable(table: &Value) -> Vec<Interactable> {
-    let mut ret_vec = vec![];
-    let table = match table.get("objects") {
-        Some(table) => table,
-        None => {
-            error!("parse_interactable: Invalid layer");
-            return ret_vec;
-        }
-    };
-    let table = match table.as_array() {
-        Some(table) => table,
-        None => {
-            error!("parse_interactable: Layer can not be represented as array");
-            return ret_vec;
+// Polylines and polygons are patrolled, rectangles are wandered around in
+fn make_path(path: &Value) -> Result<Route, MapErrorKind> {
+    let id = path["id"].as_u64().unwrap_or_default();
+    let origin = vec2(get_f32(path, "x")?, get_f32(path, "y")?);
+    let (points, closed) = match (path["polyline"].as_array(), path["polygon"].as_array()) {
+        (Some(points), _) => (points, false),
+        (None, Some(points)) => (points, true),
+        (None, None) => {
+            let region = get_rect(path)?;
+            if region.w == 0. || region.h == 0. {
+                return Err(MapErrorKind::Field("polyline"));
+            }
+            let movement = Movement::wander(region);
+            return Ok(Route { id, movement });
         }
     };
+    let points = points
+        .iter()
+        .map(|point| Ok((origin + vec2(get_f32(point, "x")?, get_f32(point, "y")?)) * RATIO))
+        .collect::<Result<Vec<Vec2>, MapErrorKind>>()?;
+    if points.is_empty() {
+        return Err(MapErrorKind::Field("polyline"));
+    }
+    let movement = Movement::patrol(points, closed);
+    Ok(Route { id, movement })
+}
 
-    for item in table {
-        match item["type"].as_str().unwrap().to_lowercase().as_str() {
-            "chest" => ret_vec.push(make_chest(item)),
-            "door" => ret_vec.push(make_door(item)),
-            x => {
-                error!("Unrecognised interactable type {}", x);
-                dbg!(item);
-            }
-        }
+fn make_interactable(item: &Value) -> Result<Interactable, MapErrorKind> {
+    let kind = item["type"].as_str().ok_or(MapErrorKind::Field("type"))?;
+    match kind.to_lowercase().as_str() {
+        "chest" => make_chest(item),
+        "door" => make_door(item),
+        x => Err(MapErrorKind::UnknownInteractable(x.to_string())),
     }
+}
 
-    ret_vec
+fn make_door(table: &Value) -> Result<Interactable, MapErrorKind> {
+    let hitbox = get_rect(table)?;
+    let command = get_command(&table["properties"]).ok_or(MapErrorKind::NoCommand)?;
+    let (map, location) = parse_command(command)?;
+
+    let id = table["id"].as_u64().unwrap_or_default();
+    let door = Door::new(id, hitbox, map, location);
+    Ok(Box::new(door))
 }
 
-fn make_door(table: &Value) -> Interactable {
-    let x = get_pos(table, "x", "make_door") * RATIO;
-    let y = get_pos(table, "y", "make_door") * RATIO;
-    let w = get_pos(table, "width", "make_door") * RATIO;
-    let h = get_pos(table, "height", "make_door") * RATIO;
-    let hitbox = Rect::new(x, y, w, h);
+fn make_chest(table: &Value) -> Result<Interactable, MapErrorKind> {
+    let x = get_f32(table, "x")? * RATIO + PIXEL;
+    let y = get_f32(table, "y")? * RATIO + PIXEL;
+    let id = table["id"].as_u64().unwrap_or_default();
+    // A loot table beats a single item, whether it exists is checked by validate-maps
+    if let Some(loot) = get_property(table, "loot") {
+        let chest = Chest::new(id, vec2(x, y), ChestContent::Loot(loot.into()));
+        return Ok(Box::new(chest));
+    }
+    // Unknown names are checked by validate-maps too, they're only looked up once it's opened
+    let item = get_item(table).map_err(|_| MapErrorKind::Field("item"))?;
+    let chest = Chest::new(id, vec2(x, y), ChestContent::Item(item));
+    Ok(Box::new(chest))
+}
 
-    let command = get_command(&table["properties"]).unwrap();
-    let commands: Vec<&str> = command.split_whitespace().collect();
-    let command = commands[0].trim().into();
-    let pos_x = commands[1].trim().parse::<f32>().unwrap() * TILE;
-    let pos_y = commands[2].trim().parse::<f32>().unwrap() * TILE;
-    let location = vec2(pos_x, pos_y);
+fn get_rect(table: &Value) -> Result<Rect, MapErrorKind> {
+    let x = get_f32(table, "x")? * RATIO;
+    let y = get_f32(table, "y")? * RATIO;
+    let w = get_f32(table, "width")? * RATIO;
+    let h = get_f32(table, "height")? * RATIO;
+    Ok(Rect::new(x, y, w, h))
+}
 
-    let door = Door::new(hitbox, command, location);
-    Box::new(door)
+fn get_f32(table: &Value, field: &'static str) -> Result<f32, MapErrorKind> {
+    let value = table[field].as_f64().ok_or(MapErrorKind::Field(field))?;
+    Ok(value as f32)
 }
 
-fn make_chest(table: &Value) -> Interactable {
-    let x = get_pos(table, "x", "make_chest") * RATIO + PIXEL;
-    let y = get_pos(table, "y", "make_chest") * RATIO + PIXEL;
-    let item = match get_item(table) {
-        Ok(item) => item,
-        Err(ItemErr::ParseErr(name)) => {
-            warn!("Invalid Item name {name}, defaulting to slime");
-            Item::slime(1)
-        }
-        Err(ItemErr::NotSameType) => {
-            warn!("Key is not of type string, defaulting to slime");
-            Item::slime(1)
-        }
-        Err(ItemErr::NoKey) => {
-            warn!("Key 'item' does not exist, defaulting to slime");
-            Item::slime(1)
-        }
-    };
-    let chest = Chest::new(vec2(x, y), item);
-    Box::new(chest)
+/// Looks up a custom property set in Tiled by its name
+pub fn get_property<'a>(table: &'a Value, name: &str) -> Option<&'a str> {
+    let props = table["properties"].as_array()?;
+    let prop = props
+        .iter()
+        .find(|prop| prop["name"].as_str() == Some(name))?;
+    prop["value"].as_str()
 }
 
-fn get_pos(table: &Value, value: &str, func: &str) -> f32 {
-    let result = match table.get(value) {
-        Some(value) => value,
-        None => {
-            warn!("{func} [WARN] Field {value} does not exist, falling back to 0 as default");
-            return 0.;
-        }
-    };
-    let value = match result.as_f64() {
-        Some(float) => float as f32,
-        None => {
-            warn!("{func} [WARN] Field {value} is not of type float, falling back to 0 as default");
-            return 0.;
-        }
+// Properties of type object, which Tiled stores as the id of the object
+fn get_object_property(table: &Value, name: &str) -> Option<u64> {
+    let props = table["properties"].as_array()?;
+    let prop = props
+        .iter()
+        .find(|prop| prop["name"].as_str() == Some(name))?;
+    prop["value"].as_u64()
+}
+
+// Tiled stores bools as actual JSON bools, unlike get_property
+fn get_bool_property(table: &Value, name: &str) -> bool {
+    let props = match table["properties"].as_array() {
+        Some(props) => props,
+        None => return false,
     };
-    value
+    props
+        .iter()
+        .find(|prop| prop["name"].as_str() == Some(name))
+        .and_then(|prop| prop["value"].as_bool())
+        .unwrap_or(false)
 }
 
-enum ItemErr {
+pub enum ItemErr {
     NoKey,
     NotSameType,
-    ParseErr(String),
 }
 
-fn get_item(table: &Value) -> Result<Item, ItemErr> {
+/// The id of the item a chest holds, which may not be in the registry
+pub fn get_item(table: &Value) -> Result<Rc<str>, ItemErr> {
     let key = table.get("properties").ok_or(ItemErr::NoKey)?;
     let key = key.as_array().ok_or(ItemErr::NoKey)?;
-    // Since tiled would not allow an array to have 0 item this code should not cause a crash
-    let key = key[0]["value"].as_str().ok_or(ItemErr::NotSameType)?;
-
-    let item = match key.to_lowercase().as_str() {
-        "rusty sword" | "rusty_sword" => Ok(Item::rusty_sword()),
-        "black sword" | "black_sword" => Ok(Item::black_sword()),
-        "slime" => Ok(Item::slime(1)),
-        "mushroom" => Ok(Item::mushroom(1)),
-        x => Err(ItemErr::ParseErr(String::from(x))),
-    };
-    item
+    let key = key.first().ok_or(ItemErr::NoKey)?;
+    let key = key["value"].as_str().ok_or(ItemErr::NotSameType)?;
+
+    // Both "black sword" and "black_sword" are fine
+    Ok(key.to_lowercase().replace(' ', "_").into())
 }
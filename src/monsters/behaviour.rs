@@ -0,0 +1,180 @@
+use macroquad::prelude::*;
+use macroquad::rand::gen_range;
+use serde_json::Value;
+
+use crate::logic::*;
+
+// Close enough to a point to count as being there
+const ARRIVE_DISTANCE: f32 = TILE / 2.;
+// Wandering is a stroll, not a sprint
+const WANDER_SPEED: f32 = 0.5;
+
+/// How a kind of monster acts, from the "behaviour" table of its definition. Distances are in
+/// world units like tracking_range, times are in seconds
+#[derive(Clone, Debug)]
+pub struct Behaviour {
+    pub tracking_range: f32,
+    pub wander_radius: f32,
+    pub wander_pause: f32,
+    // How far from home the monster will chase before giving up
+    pub leash_range: f32,
+    // Melee monsters also need their damage box on the player before they wind up
+    pub attack_range: f32,
+    pub wind_up: f32,
+    pub attack_cooldown: f32,
+    // Fraction of max health under which it runs away, 0 means never
+    pub flee_below: f32,
+    // Ranged monsters back off while reloading if the player gets closer than this
+    pub keep_distance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MobState {
+    Idle(Timer),
+    Wander(Vec2),
+    Chase,
+    WindUp(Timer),
+    Cooldown(Timer),
+    Return,
+    Flee,
+}
+
+/// What the monster should do this tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Stand,
+    // Where to, and how much of its speed to use
+    MoveTo(Vec2, f32),
+    MoveAway(Vec2),
+    // The wind up is over, hit whatever is in the damage box
+    Strike,
+}
+
+#[derive(Clone, Debug)]
+pub struct Brain {
+    pub state: MobState,
+    pub home: Vec2,
+}
+
+impl Behaviour {
+    pub fn from_json(json: &Value, tracking_range: f32) -> Self {
+        let get = |field: &str, default: f32| {
+            json[field]
+                .as_f64()
+                .map(|num| num as f32)
+                .unwrap_or(default)
+        };
+        Self {
+            tracking_range,
+            wander_radius: get("wander_radius", 2. * TILE),
+            wander_pause: get("wander_pause", 2.),
+            leash_range: get("leash_range", tracking_range * 2.),
+            attack_range: get("attack_range", TILE),
+            wind_up: get("wind_up", 0.4),
+            attack_cooldown: get("attack_cooldown", 1.),
+            flee_below: get("flee_below", 0.),
+            keep_distance: get("keep_distance", 0.),
+        }
+    }
+}
+
+impl Brain {
+    pub fn new(home: Vec2) -> Self {
+        Self {
+            state: MobState::Idle(Timer::new(0.)),
+            home,
+        }
+    }
+
+    /// Moves the state machine along. me and player are the centers of the two hitboxes, in_reach
+    /// is whether a strike would land from here
+    pub fn think(
+        &mut self,
+        behaviour: &Behaviour,
+        me: Vec2,
+        player: Vec2,
+        in_reach: bool,
+        health: f32,
+        dt: f32,
+    ) -> Action {
+        let to_player = me.distance(player);
+        let sees_player = to_player <= behaviour.tracking_range;
+        let too_far = me.distance(self.home) > behaviour.leash_range;
+        let scared = health < behaviour.flee_below;
+
+        // Running away beats everything else, and stops once the player is out of sight
+        if scared && sees_player {
+            self.state = MobState::Flee;
+        } else if self.state == MobState::Flee {
+            self.state = MobState::Return;
+        }
+
+        match &mut self.state {
+            MobState::Idle(timer) => {
+                timer.tick(dt);
+                if sees_player && !scared {
+                    self.state = MobState::Chase;
+                } else if timer.is_done() {
+                    self.state = MobState::Wander(self.wander_target(behaviour));
+                }
+                Action::Stand
+            }
+            MobState::Wander(target) => {
+                let target = *target;
+                if sees_player && !scared {
+                    self.state = MobState::Chase;
+                } else if me.distance(target) < ARRIVE_DISTANCE {
+                    self.state = MobState::Idle(Timer::new(behaviour.wander_pause));
+                }
+                Action::MoveTo(target, WANDER_SPEED)
+            }
+            MobState::Chase => {
+                if too_far || !sees_player {
+                    self.state = MobState::Return;
+                    return Action::MoveTo(self.home, 1.);
+                }
+                if to_player <= behaviour.attack_range && in_reach {
+                    self.state = MobState::WindUp(Timer::new(behaviour.wind_up));
+                    return Action::Stand;
+                }
+                Action::MoveTo(player, 1.)
+            }
+            MobState::WindUp(timer) => {
+                timer.tick(dt);
+                if !timer.is_done() {
+                    return Action::Stand;
+                }
+                self.state = MobState::Cooldown(Timer::new(behaviour.attack_cooldown));
+                Action::Strike
+            }
+            MobState::Cooldown(timer) => {
+                timer.tick(dt);
+                if timer.is_done() {
+                    self.state = MobState::Chase;
+                }
+                match to_player < behaviour.keep_distance {
+                    true => Action::MoveAway(player),
+                    false => Action::Stand,
+                }
+            }
+            // Leashed monsters ignore the player until they're home
+            MobState::Return => {
+                if me.distance(self.home) < ARRIVE_DISTANCE {
+                    self.state = MobState::Idle(Timer::new(behaviour.wander_pause));
+                    return Action::Stand;
+                }
+                Action::MoveTo(self.home, 1.)
+            }
+            MobState::Flee => Action::MoveAway(player),
+        }
+    }
+
+    fn wander_target(&self, behaviour: &Behaviour) -> Vec2 {
+        let radius = behaviour.wander_radius;
+        self.home + vec2(gen_range(-radius, radius), gen_range(-radius, radius))
+    }
+
+    pub fn is_winding_up(&self) -> bool {
+        matches!(self.state, MobState::WindUp(_))
+    }
+}
@@ -0,0 +1,210 @@
+use super::definition::{MonsterDef, MonsterDefs};
+use super::mob::Mob;
+use super::*;
+use crate::flags::Flags;
+use crate::map::{Area, Monster};
+use std::rc::Rc;
+
+/// A monster that changes how it fights as it loses health, see MonsterDef::phases
+pub struct Boss {
+    mob: Mob,
+    base: Rc<MonsterDef>,
+    // How many phases it has gone through
+    phase: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fight {
+    Waiting,
+    Fighting,
+    Won,
+}
+
+/// A room from the Bosses layer. The boss shows up once the player walks in, and every way out of
+/// the map stays shut until it's dead
+#[derive(Clone, Debug)]
+pub struct BossArena {
+    // The Tiled object id, so saves can tell which bosses are dead
+    pub id: u64,
+    pub kind: Rc<str>,
+    pub arena: Rect,
+    // Tiled object id of the chest that stays locked until the boss is beaten
+    pub reward: Option<u64>,
+    // Story flag set once the boss is beaten
+    pub flag: Option<Rc<str>>,
+    pub fight: Fight,
+}
+
+impl Boss {
+    pub fn new(def: Rc<MonsterDef>, pos: Vec2) -> Self {
+        Self {
+            mob: Mob::new(def.clone(), pos),
+            base: def,
+            phase: 0,
+        }
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    fn next_phase(&mut self) {
+        let health = self.mob.get_props().health / self.max_health();
+        // Could skip a phase or two if it took a big enough hit
+        while let Some(next) = self.base.phases.get(self.phase) {
+            if health >= next.below {
+                break;
+            }
+            self.mob.change_def(next.def.clone());
+            self.phase += 1;
+        }
+    }
+}
+
+impl IsAMonster for Boss {
+    fn tick(
+        &mut self,
+        player: &mut Player,
+        walls: &Walls,
+        nav: &NavGrid,
+        projectiles: &mut Vec<Projectile>,
+        dt: f32,
+    ) {
+        self.mob.tick(player, walls, nav, projectiles, dt);
+        if self.mob.get_props().health > 0. {
+            self.next_phase()
+        }
+    }
+
+    fn tick_anim(&mut self) {
+        self.mob.tick_anim()
+    }
+
+    fn damage_player(&self, player: &mut Player) {
+        self.mob.damage_player(player)
+    }
+
+    // The health bar is on the HUD instead
+    fn draw(&self, texture: &Textures) {
+        self.mob.draw_sprite(texture)
+    }
+
+    fn get_props(&self) -> &Props {
+        self.mob.get_props()
+    }
+
+    fn get_mut_props(&mut self) -> &mut Props {
+        self.mob.get_mut_props()
+    }
+
+    fn name(&self) -> &str {
+        self.mob.name()
+    }
+
+    fn max_health(&self) -> f32 {
+        self.base.health
+    }
+
+    fn loot(&self) -> Option<&str> {
+        self.base.loot.as_deref()
+    }
+
+    fn boss_title(&self) -> Option<&str> {
+        Some(&self.base.title)
+    }
+}
+
+impl Collidable for Boss {
+    fn mut_pos(&mut self) -> &mut Vec2 {
+        self.mob.mut_pos()
+    }
+
+    fn hitbox(&self) -> Rect {
+        self.mob.hitbox()
+    }
+
+    fn pos(&self) -> Vec2 {
+        self.mob.pos()
+    }
+}
+
+impl Entity for Boss {}
+
+impl BossArena {
+    pub fn new(id: u64, kind: &str, arena: Rect, reward: Option<u64>, flag: Option<&str>) -> Self {
+        Self {
+            id,
+            kind: kind.into(),
+            arena,
+            reward,
+            flag: flag.map(|flag| flag.into()),
+            fight: Fight::Waiting,
+        }
+    }
+}
+
+impl Area {
+    /// Starts a fight once the player walks into an arena, and ends it when the boss is gone
+    pub fn tick_bosses(&mut self, player: Rect, defs: &MonsterDefs, flags: &mut Flags) {
+        let boss_alive = self.boss().is_some();
+        let mut changed = false;
+        for arena in self.bosses.iter_mut() {
+            match arena.fight {
+                Fight::Waiting if arena.arena.contains(player.center()) => {
+                    changed = true;
+                    let def = match defs.get(&arena.kind) {
+                        Some(def) => def,
+                        None => {
+                            // validate-maps catches this, nothing to do but let the player through
+                            let kind = &arena.kind;
+                            warn!("unknown boss type {kind}");
+                            arena.fight = Fight::Won;
+                            continue;
+                        }
+                    };
+                    let pos = arena.arena.center() - def.hitbox.center();
+                    self.enemies.push(Box::new(Boss::new(def.clone(), pos)));
+                    arena.fight = Fight::Fighting;
+                }
+                Fight::Fighting if !boss_alive => {
+                    changed = true;
+                    arena.fight = Fight::Won;
+                    if let Some(flag) = &arena.flag {
+                        flags.set(flag, 1)
+                    }
+                }
+                _ => (),
+            }
+        }
+        if changed {
+            self.update_locks()
+        }
+    }
+
+    /// The boss being fought right now
+    pub fn boss(&self) -> Option<&Monster> {
+        self.enemies
+            .iter()
+            .find(|monster| monster.boss_title().is_some())
+    }
+
+    /// Gates and doors don't work during a boss fight
+    pub fn is_sealed(&self) -> bool {
+        self.bosses
+            .iter()
+            .any(|arena| arena.fight == Fight::Fighting)
+    }
+
+    /// For when the player leaves in the middle of a fight, which can only happen by dying. The
+    /// boss starts over next time
+    pub fn abandon_fights(&mut self) {
+        self.enemies
+            .retain(|monster| monster.boss_title().is_none());
+        for arena in self.bosses.iter_mut() {
+            if arena.fight == Fight::Fighting {
+                arena.fight = Fight::Waiting
+            }
+        }
+        self.update_locks()
+    }
+}
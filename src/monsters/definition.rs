@@ -0,0 +1,280 @@
+use macroquad::experimental::animation::{AnimatedSprite, Animation};
+use macroquad::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::rc::Rc;
+
+use super::behaviour::Behaviour;
+use crate::logic::*;
+use crate::map::{MapErrorKind, MapLoadError, ProjectileSprite};
+use crate::player::PIXEL;
+
+/// Every monster in assets/monsters, by file name
+pub type MonsterDefs = HashMap<Rc<str>, Rc<MonsterDef>>;
+
+/// What a kind of monster is like. The numbers are in the same units as the rest of the game,
+/// except boxes which are in pixels of the monster's sprite
+#[derive(Debug)]
+pub struct MonsterDef {
+    pub name: Rc<str>,
+    pub texture: Rc<str>,
+    pub health: f32,
+    pub speed: f32,
+    pub damage: f32,
+    pub behaviour: Behaviour,
+    // Relative to the monster's position, already scaled to world size
+    pub hitbox: Rect,
+    pub damage_box: Rect,
+    frame_size: (u32, u32),
+    animations: Vec<Animation>,
+    // Monsters without a dying animation disappear straight away
+    pub death_time: f32,
+    // Name of a table in assets/loot
+    pub loot: Option<Rc<str>>,
+    // Monsters that have this shoot instead of hitting
+    pub projectile: Option<ProjectileDef>,
+    // So one sprite sheet can be used for a few different monsters
+    pub tint: Color,
+    // How much bigger than its sprite the monster is drawn, boxes are scaled along with it
+    pub scale: f32,
+    // What the boss health bar calls it, the file name if not set
+    pub title: Rc<str>,
+    // Only used when the monster is a boss, sorted from the highest threshold down
+    pub phases: Vec<Phase>,
+}
+
+/// Once a boss's health drops under below (a fraction of max health) it acts like def instead
+#[derive(Debug)]
+pub struct Phase {
+    pub below: f32,
+    pub def: Rc<MonsterDef>,
+}
+
+#[derive(Debug)]
+pub struct ProjectileDef {
+    pub sprite: ProjectileSprite,
+    pub speed: f32,
+    pub damage: f32,
+    pub life_time: f32,
+}
+
+pub const IDLE_ANIM: usize = 0;
+pub const MOVING_ANIM: usize = 1;
+pub const DYING_ANIM: usize = 2;
+
+impl MonsterDef {
+    pub fn from_json(name: &str, json: &Value) -> Result<Self, MapErrorKind> {
+        let texture = json["texture"]
+            .as_str()
+            .ok_or(MapErrorKind::Field("texture"))?;
+        let scale = json["scale"].as_f64().unwrap_or(1.) as f32;
+        let scaled = |rect: Rect| {
+            Rect::new(
+                rect.x * scale,
+                rect.y * scale,
+                rect.w * scale,
+                rect.h * scale,
+            )
+        };
+        let hitbox = read_box(&json["hitbox"]).ok_or(MapErrorKind::Field("hitbox"))?;
+        let damage_box = match json["damage_box"].is_null() {
+            true => hitbox,
+            false => read_box(&json["damage_box"]).ok_or(MapErrorKind::Field("damage_box"))?,
+        };
+        let (hitbox, damage_box) = (scaled(hitbox), scaled(damage_box));
+        let frame_size = json["frame_size"]
+            .as_array()
+            .and_then(|size| Some((size.first()?.as_u64()?, size.get(1)?.as_u64()?)))
+            .ok_or(MapErrorKind::Field("frame_size"))?;
+
+        let anims = &json["animations"];
+        let mut animations = vec![
+            read_anim("idle", &anims["idle"]).ok_or(MapErrorKind::Field("animations.idle"))?,
+            read_anim("moving", &anims["moving"])
+                .ok_or(MapErrorKind::Field("animations.moving"))?,
+        ];
+        if !anims["dying"].is_null() {
+            let dying = read_anim("dying", &anims["dying"]);
+            animations.push(dying.ok_or(MapErrorKind::Field("animations.dying"))?);
+        }
+
+        // Whether the table exists is up to validate-maps, like spawner types
+        let loot = match json["loot"].is_null() {
+            true => None,
+            false => Some(
+                json["loot"]
+                    .as_str()
+                    .ok_or(MapErrorKind::Field("loot"))?
+                    .into(),
+            ),
+        };
+
+        let projectile = match json["projectile"].is_null() {
+            true => None,
+            false => Some(read_projectile(&json["projectile"])?),
+        };
+        let tint = match json["tint"].as_array() {
+            Some(tint) => {
+                let channel = |i: usize| tint.get(i).and_then(|c| c.as_f64()).unwrap_or(1.) as f32;
+                Color::new(channel(0), channel(1), channel(2), 1.)
+            }
+            None => WHITE,
+        };
+        let title = json["title"].as_str().unwrap_or(name);
+
+        let mut phases = vec![];
+        for phase in json["phases"].as_array().unwrap_or(&vec![]) {
+            phases.push(read_phase(name, json, phase)?);
+        }
+        phases.sort_by(|a, b| b.below.total_cmp(&a.below));
+
+        Ok(Self {
+            name: name.into(),
+            texture: texture.into(),
+            health: get_number(json, "health")?,
+            speed: get_number(json, "speed")?,
+            behaviour: Behaviour::from_json(
+                &json["behaviour"],
+                get_number(json, "tracking_range")?,
+            ),
+            damage: get_number(json, "damage")?,
+            hitbox,
+            damage_box,
+            frame_size: (frame_size.0 as u32, frame_size.1 as u32),
+            animations,
+            death_time: json["death_time"].as_f64().unwrap_or(0.) as f32,
+            loot,
+            projectile,
+            tint,
+            scale,
+            title: title.into(),
+            phases,
+        })
+    }
+
+    pub fn animation(&self) -> AnimatedSprite {
+        let (width, height) = self.frame_size;
+        AnimatedSprite::new(width, height, &self.animations, true)
+    }
+
+    pub fn has_dying_anim(&self) -> bool {
+        self.animations.len() > DYING_ANIM
+    }
+}
+
+/// Reads every monster definition, reporting all the broken ones at once
+pub fn load_monsters() -> Result<MonsterDefs, Vec<MapLoadError>> {
+    let mut monsters = MonsterDefs::new();
+    let mut errors = vec![];
+
+    for path in get_path("assets/monsters/", ".json").map_err(|err| vec![err])? {
+        let file = path.to_string_lossy();
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let result = read_to_string(&path)
+            .map_err(|err| MapErrorKind::Io(err.to_string()))
+            .and_then(|json| {
+                serde_json::from_str(&json).map_err(|err| MapErrorKind::Json(err.to_string()))
+            })
+            .and_then(|json: Value| MonsterDef::from_json(name, &json));
+        match result {
+            Ok(def) => {
+                monsters.insert(def.name.clone(), Rc::new(def));
+            }
+            Err(kind) => errors.push(MapLoadError::new(kind).in_file(&file)),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(monsters),
+        false => Err(errors),
+    }
+}
+
+fn get_number(json: &Value, field: &'static str) -> Result<f32, MapErrorKind> {
+    json[field]
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or(MapErrorKind::Field(field))
+}
+
+// [x, y, w, h] in sprite pixels
+fn read_box(json: &Value) -> Option<Rect> {
+    let nums: Option<Vec<f32>> = json
+        .as_array()?
+        .iter()
+        .map(|num| num.as_f64().map(|num| num as f32 * PIXEL))
+        .collect();
+    match nums?.as_slice() {
+        [x, y, w, h] => Some(Rect::new(*x, *y, *w, *h)),
+        _ => None,
+    }
+}
+
+// Every phase starts from the monster's own fields and swaps in whatever the phase sets. Tables
+// like behaviour are merged one level deep, so a phase only needs to list what changes
+fn read_phase(name: &str, json: &Value, phase: &Value) -> Result<Phase, MapErrorKind> {
+    let below = phase["below"]
+        .as_f64()
+        .ok_or(MapErrorKind::Field("phases.below"))? as f32;
+    let changes = phase.as_object().ok_or(MapErrorKind::Field("phases"))?;
+
+    let mut merged = json.clone();
+    merged["phases"] = Value::Null;
+    for (key, value) in changes {
+        // Switching phase shouldn't heal the boss
+        if key == "below" || key == "health" {
+            continue;
+        }
+        match (merged[key].as_object_mut(), value.as_object()) {
+            (Some(table), Some(changes)) => {
+                for (field, value) in changes {
+                    table.insert(field.clone(), value.clone());
+                }
+            }
+            _ => merged[key] = value.clone(),
+        }
+    }
+    let def = MonsterDef::from_json(name, &merged)?;
+    Ok(Phase {
+        below,
+        def: Rc::new(def),
+    })
+}
+
+fn read_projectile(json: &Value) -> Result<ProjectileDef, MapErrorKind> {
+    let texture = json["texture"]
+        .as_str()
+        .ok_or(MapErrorKind::Field("projectile.texture"))?;
+    // Unlike boxes this is in pixels of the texture, since it's a source rect
+    let source = read_box(&json["source"])
+        .map(|rect| {
+            Rect::new(
+                rect.x / PIXEL,
+                rect.y / PIXEL,
+                rect.w / PIXEL,
+                rect.h / PIXEL,
+            )
+        })
+        .ok_or(MapErrorKind::Field("projectile.source"))?;
+    let sprite = ProjectileSprite {
+        texture: texture.into(),
+        source,
+    };
+    Ok(ProjectileDef {
+        sprite,
+        speed: get_number(json, "speed")?,
+        damage: get_number(json, "damage")?,
+        life_time: json["life_time"].as_f64().unwrap_or(1.) as f32,
+    })
+}
+
+fn read_anim(name: &str, json: &Value) -> Option<Animation> {
+    let row = json["row"].as_u64()? as u32;
+    let frames = json["frames"].as_u64()? as u32;
+    let fps = json["fps"].as_u64()? as u32;
+    Some(make_anim(name, row, frames, fps))
+}
@@ -0,0 +1,248 @@
+use crate::logic::*;
+use crate::map::Projectile;
+use crate::navigation::NavGrid;
+use crate::player::{Collidable, Player, Props};
+use crate::spatial::Walls;
+use macroquad::prelude::*;
+use std::rc::Rc;
+
+use super::behaviour::{Action, Brain};
+use super::definition::*;
+use super::{Entity, IsAMonster};
+
+/// Any monster from assets/monsters, everything about it comes from its definition
+#[derive(Clone)]
+pub struct Mob {
+    def: Rc<MonsterDef>,
+    props: Props,
+    death_timer: Timer,
+    brain: Brain,
+    // Tiles to walk through when the target can't be seen, closest first
+    path: Vec<Vec2>,
+    path_goal: Option<(i32, i32)>,
+    repath: Timer,
+    // Only set for monsters from a wave spawner, see Waves
+    pub wave: Option<Rc<()>>,
+}
+
+// A* is not cheap, so a path is kept around for a while unless the target moves to another tile
+const REPATH_TIME: f32 = 0.5;
+
+impl IsAMonster for Mob {
+    fn tick(
+        &mut self,
+        player: &mut Player,
+        walls: &Walls,
+        nav: &NavGrid,
+        projectiles: &mut Vec<Projectile>,
+        dt: f32,
+    ) {
+        if self.props.health <= 0. {
+            // Used to wait on the animation frame but the animation is not ticked when headless
+            self.death_timer.tick(dt);
+            if self.death_timer.is_done() {
+                self.props.should_despawn = true
+            }
+            return;
+        }
+        let me = self.hitbox().center();
+        let health = self.props.health / self.def.health;
+        // Being in attack_range is enough to shoot, but a bite has to actually touch the player
+        let in_reach = match &self.def.projectile {
+            Some(_) => true,
+            None => self.damage_box().overlaps(&player.hitbox()),
+        };
+        let behaviour = &self.def.behaviour;
+        let action = self
+            .brain
+            .think(behaviour, me, player.pos(), in_reach, health, dt);
+        match action {
+            Action::Stand => (),
+            Action::MoveTo(target, speed) => {
+                let waypoint = self.waypoint(me, target, nav, dt);
+                self.steer(waypoint - me, self.def.speed * speed)
+            }
+            Action::MoveAway(from) => self.steer(me - from, self.def.speed),
+            Action::Strike => match &self.def.projectile {
+                // Shooting at a wall would be a waste
+                Some(shot) if nav.line_of_sight(me, player.pos()) => {
+                    let velocity = (player.pos() - me).normalize_or_zero() * shot.speed;
+                    let sprite = shot.sprite.clone();
+                    projectiles.push(Projectile::hostile(
+                        me,
+                        velocity,
+                        shot.damage,
+                        shot.life_time,
+                        sprite,
+                    ))
+                }
+                Some(_) => (),
+                None => self.damage_player(player),
+            },
+        }
+        self.props.new_pos(dt);
+        self.wall_collsion(walls);
+    }
+
+    fn tick_anim(&mut self) {
+        self.props.animation.update();
+        if self.props.health <= 0. {
+            if !self.def.has_dying_anim() {
+                return;
+            }
+            if self.props.animation.current_animation() != DYING_ANIM {
+                self.props.animation.set_animation(DYING_ANIM);
+                self.props.animation.set_frame(0);
+            }
+            return;
+        }
+        if !self.props.is_moving() {
+            self.props.animation.set_animation(IDLE_ANIM);
+        } else {
+            self.props.animation.set_animation(MOVING_ANIM);
+        }
+        if self.props.velocity.x > 0. {
+            self.props.flip_sprite = false
+        }
+        if self.props.velocity.x < 0. {
+            self.props.flip_sprite = true
+        }
+    }
+
+    fn max_health(&self) -> f32 {
+        self.def.health
+    }
+
+    fn damage_player(&self, player: &mut Player) {
+        if self.damage_box().overlaps(&player.hitbox()) {
+            player.hurt(self.def.damage);
+        }
+    }
+
+    fn draw(&self, texture: &Textures) {
+        self.draw_sprite(texture);
+        self.draw_health_bar(&texture["ui"])
+    }
+
+    fn get_props(&self) -> &Props {
+        &self.props
+    }
+
+    fn get_mut_props(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn name(&self) -> &str {
+        &self.def.name
+    }
+
+    fn loot(&self) -> Option<&str> {
+        self.def.loot.as_deref()
+    }
+}
+
+impl Mob {
+    pub fn new(def: Rc<MonsterDef>, pos: Vec2) -> Self {
+        let props = Props::from(pos, def.health, def.animation());
+        let death_timer = Timer::new(def.death_time);
+        let brain = Brain::new(def.hitbox.offset(pos).center());
+        Mob {
+            def,
+            props,
+            death_timer,
+            brain,
+            path: vec![],
+            path_goal: None,
+            repath: Timer::new(REPATH_TIME),
+            wave: None,
+        }
+    }
+
+    /// Everything draw does minus the health bar
+    pub fn draw_sprite(&self, texture: &Textures) {
+        let dest_size =
+            Some(self.props.animation.frame().dest_size * SCALE_FACTOR * self.def.scale);
+        let source = Some(self.props.animation.frame().source_rect);
+        let draw_param = DrawTextureParams {
+            source,
+            dest_size,
+            flip_x: self.props.flip_sprite,
+            ..Default::default()
+        };
+        // Flashes red while winding up, so there's time to get out of the way
+        let color = match self.brain.is_winding_up() {
+            true => Color::new(1., 0.5, 0.5, 1.),
+            false => self.def.tint,
+        };
+        if let Some(texture) = texture.get(&self.def.texture) {
+            draw_texture_ex(
+                texture,
+                self.props.render_pos.x,
+                self.props.render_pos.y,
+                color,
+                draw_param,
+            );
+        }
+    }
+
+    pub fn def(&self) -> &Rc<MonsterDef> {
+        &self.def
+    }
+
+    /// Makes the monster act like another definition from now on, health is kept as is
+    pub fn change_def(&mut self, def: Rc<MonsterDef>) {
+        self.props.animation = def.animation();
+        self.def = def;
+    }
+
+    // Anything the player has to be touching for a melee hit to land
+    fn damage_box(&self) -> Rect {
+        self.def.damage_box.offset(self.props.pos)
+    }
+
+    // Where to head next on the way to target, which is target itself if nothing is in the way
+    fn waypoint(&mut self, me: Vec2, target: Vec2, nav: &NavGrid, dt: f32) -> Vec2 {
+        if nav.line_of_sight(me, target) {
+            self.path.clear();
+            self.path_goal = None;
+            return target;
+        }
+        self.repath.tick(dt);
+        let goal = NavGrid::cell_of(target);
+        if self.repath.is_done() || self.path_goal != Some(goal) {
+            self.repath.repeat();
+            self.path = nav.find_path(me, target).unwrap_or_default();
+            self.path_goal = Some(goal);
+        }
+        while self
+            .path
+            .first()
+            .is_some_and(|next| me.distance(*next) < TILE / 3.)
+        {
+            self.path.remove(0);
+        }
+        // No way there at all, so just try going straight at it
+        self.path.first().copied().unwrap_or(target)
+    }
+
+    // Same as Props::move_to, but the direction is already worked out
+    fn steer(&mut self, direction: Vec2, speed: f32) {
+        self.props.velocity += direction.normalize_or_zero() * speed
+    }
+}
+
+impl Collidable for Mob {
+    fn mut_pos(&mut self) -> &mut Vec2 {
+        &mut self.props.pos
+    }
+
+    fn hitbox(&self) -> Rect {
+        self.def.hitbox.offset(self.props.pos)
+    }
+
+    fn pos(&self) -> Vec2 {
+        self.props.pos
+    }
+}
+
+impl Entity for Mob {}
@@ -1,23 +1,39 @@
-use crate::ui::items::Item;
+use crate::map::Projectile;
+use crate::navigation::NavGrid;
+use crate::spatial::Walls;
 use crate::{logic::*, player::*};
 use macroquad::prelude::*;
-use spawner::MobType;
 
-pub mod mushroom;
-pub mod slime;
+pub mod behaviour;
+pub mod boss;
+pub mod definition;
+pub mod mob;
 pub mod spawner;
 
 pub trait IsAMonster {
-    fn loot(&self) -> Option<Item>;
-    fn tick(&mut self, player: &mut Player, walls: &[Rect]);
+    // Which loot table it drops from when it dies
+    fn loot(&self) -> Option<&str>;
+    // Anything the monster shoots goes into projectiles
+    fn tick(
+        &mut self,
+        player: &mut Player,
+        walls: &Walls,
+        nav: &NavGrid,
+        projectiles: &mut Vec<Projectile>,
+        dt: f32,
+    );
     fn tick_anim(&mut self);
     fn damage_player(&self, player: &mut Player);
-    fn move_to(&mut self, player_pos: Vec2);
     fn draw(&self, texture: &Textures);
     fn get_props(&self) -> &Props;
     fn get_mut_props(&mut self) -> &mut Props;
-    fn get_type(&self) -> MobType;
+    // Which definition in assets/monsters this is
+    fn name(&self) -> &str;
     fn max_health(&self) -> f32;
+    // Only bosses have one, it goes on their health bar
+    fn boss_title(&self) -> Option<&str> {
+        None
+    }
     fn draw_health_bar(&self, texture: &Texture2D) {
         let props = self.get_props();
         if props.health == self.max_health() {
@@ -38,17 +54,12 @@ pub trait IsAMonster {
             dest_size,
             ..Default::default()
         };
-        draw_texture_ex(
-            texture,
-            props.pos.x,
-            props.pos.y - TILE / 2.,
-            WHITE,
-            draw_param,
-        );
+        let pos = props.render_pos;
+        draw_texture_ex(texture, pos.x, pos.y - TILE / 2., WHITE, draw_param);
         let health_percentage = props.health / self.max_health();
         let heath_bar = Rect::new(
-            props.pos.x + PIXEL,
-            props.pos.y - PIXEL,
+            pos.x + PIXEL,
+            pos.y - PIXEL,
             22. * health_percentage * PIXEL,
             2. * PIXEL,
         );
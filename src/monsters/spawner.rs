@@ -1,33 +1,59 @@
 use macroquad::prelude::*;
 use macroquad::rand::*;
+use std::f32::consts::TAU;
+use std::rc::Rc;
 
 use crate::logic::Timer;
-use crate::map::Monster;
+use crate::map::{MapErrorKind, Monster};
 
-use super::mushroom::Mushroom;
-use super::slime::Slime;
+use super::definition::MonsterDefs;
+use super::mob::Mob;
 
-// The term "radius" being used here is technically incorrect as it is more of a square than a circle but
-// it would take an additional layer of complexity to fix this tiny bug, which I'm not very fond of
-// dealing with so "radius" remains for now.
 #[derive(Debug)]
 pub struct Spawner {
-    kind: MobType,
+    // The name of a monster in assets/monsters, wave spawners have theirs in each wave
+    pub kind: Rc<str>,
     pub spawn_radius: f32,
-    max_mob: u32,
-    timer: Timer,
+    pub mode: SpawnMode,
     pos: Vec2,
 }
 
-#[derive(PartialEq, Debug)]
-pub enum MobType {
-    Slime,
-    Mushroom,
+#[derive(Debug)]
+pub enum SpawnMode {
+    // Tops the area back up every time the timer runs out
+    Timer { max_mob: u32, timer: Timer },
+    Waves(Waves),
+}
+
+/// Which monsters and how many of each
+pub type Wave = Vec<(Rc<str>, u32)>;
+
+/// Sends the waves one after another once the player walks into region, each one as soon as the
+/// last is dead
+#[derive(Debug)]
+pub struct Waves {
+    // The Tiled object id, so saves can tell which ones are cleared
+    pub id: u64,
+    pub waves: Vec<Wave>,
+    pub region: Rect,
+    // Tiled object id of a door or chest that stays locked until every wave is dead
+    pub unlocks: Option<u64>,
+    pub state: WaveState,
+    // Every monster from the current wave holds a copy, so the count says how many are left
+    alive: Rc<()>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WaveState {
+    Waiting,
+    // Which wave is out right now
+    Running(usize),
+    Cleared,
 }
 
 impl Spawner {
     pub fn new(
-        kind: MobType,
+        kind: Rc<str>,
         spawn_radius: f32,
         max_mob: u32,
         max_cooldown: f32,
@@ -38,59 +64,56 @@ impl Spawner {
         Spawner {
             kind,
             spawn_radius,
-            max_mob,
-            timer,
+            mode: SpawnMode::Timer { max_mob, timer },
             pos,
         }
     }
 
-    pub fn tick(&mut self, monsters: &mut Vec<Monster>) {
-        self.timer.tick();
-        if !self.timer.is_done() {
-            return;
-        }
-        self.timer.repeat();
-
-        if self.count_mob(&monsters) > self.max_mob {
-            return;
+    pub fn waves(waves: Waves, spawn_radius: f32, pos: Vec2) -> Self {
+        Spawner {
+            kind: "".into(),
+            spawn_radius,
+            mode: SpawnMode::Waves(waves),
+            pos,
         }
+    }
 
-        let num_mobs = rand() % self.max_mob;
-
-        for _ in 0..num_mobs {
-            let x_offset = gen_range(-self.spawn_radius, self.spawn_radius);
-            let y_offset = gen_range(-self.spawn_radius, self.spawn_radius);
-
-            match self.kind {
-                MobType::Slime => {
-                    let slime = Slime::from(vec2(self.pos.x + x_offset, self.pos.y + y_offset));
-                    monsters.push(Box::new(slime));
+    /// True on the tick the last wave is cleared
+    pub fn tick(
+        &mut self,
+        monsters: &mut Vec<Monster>,
+        defs: &MonsterDefs,
+        player: Rect,
+        dt: f32,
+    ) -> bool {
+        let (pos, radius) = (self.pos, self.spawn_radius);
+        match &mut self.mode {
+            SpawnMode::Timer { max_mob, timer } => {
+                timer.tick(dt);
+                if !timer.is_done() {
+                    return false;
                 }
-                MobType::Mushroom => {
-                    let mushroom =
-                        Mushroom::from(vec2(self.pos.x + x_offset, self.pos.y + y_offset));
-                    monsters.push(Box::new(mushroom))
+                timer.repeat();
+                let max_mob = *max_mob;
+
+                if self.count_mob(monsters) > max_mob {
+                    return false;
                 }
+                let num_mobs = rand() % max_mob;
+                spawn(monsters, defs, &self.kind, num_mobs, pos, radius, None);
+                false
             }
+            SpawnMode::Waves(waves) => waves.tick(monsters, defs, player, pos, radius),
         }
     }
 
     fn count_mob(&self, mobs: &[Monster]) -> u32 {
-        let spawner_detect_box = Rect::new(
-            self.pos.x - self.spawn_radius,
-            self.pos.y - self.spawn_radius,
-            self.spawn_radius * 2.,
-            self.spawn_radius * 2.,
-        );
         let mut num_mobs = 0;
         for mob in mobs {
-            let mob_hitbox = mob.hitbox();
-
             if !self.is_same_type(mob) {
                 continue;
             }
-
-            if spawner_detect_box.overlaps(&mob_hitbox) {
+            if mob.hitbox().center().distance(self.pos) <= self.spawn_radius {
                 num_mobs += 1;
             }
         }
@@ -98,7 +121,98 @@ impl Spawner {
     }
 
     fn is_same_type(&self, mob: &Monster) -> bool {
-        let mob_type = mob.get_type();
-        mob_type == self.kind
+        mob.name() == &*self.kind
+    }
+}
+
+impl Waves {
+    pub fn new(id: u64, waves: Vec<Wave>, region: Rect, unlocks: Option<u64>) -> Self {
+        Self {
+            id,
+            waves,
+            region,
+            unlocks,
+            state: WaveState::Waiting,
+            alive: Rc::new(()),
+        }
+    }
+
+    fn tick(
+        &mut self,
+        monsters: &mut Vec<Monster>,
+        defs: &MonsterDefs,
+        player: Rect,
+        pos: Vec2,
+        radius: f32,
+    ) -> bool {
+        let next = match self.state {
+            WaveState::Waiting if self.region.contains(player.center()) => 0,
+            WaveState::Running(wave) if Rc::strong_count(&self.alive) == 1 => wave + 1,
+            _ => return false,
+        };
+        let wave = match self.waves.get(next) {
+            Some(wave) => wave,
+            None => {
+                self.state = WaveState::Cleared;
+                return true;
+            }
+        };
+        for (kind, count) in wave {
+            let alive = Some(&self.alive);
+            spawn(monsters, defs, kind, *count, pos, radius, alive);
+        }
+        self.state = WaveState::Running(next);
+        false
+    }
+}
+
+fn spawn(
+    monsters: &mut Vec<Monster>,
+    defs: &MonsterDefs,
+    kind: &str,
+    count: u32,
+    pos: Vec2,
+    radius: f32,
+    alive: Option<&Rc<()>>,
+) {
+    // validate-maps catches these, so just don't spawn anything
+    let def = match defs.get(kind) {
+        Some(def) => def,
+        None => {
+            warn!("spawner wants unknown monster {kind}");
+            return;
+        }
+    };
+    for _ in 0..count {
+        // The square root keeps them from bunching up in the middle
+        let angle = gen_range(0., TAU);
+        let distance = gen_range(0f32, 1.).sqrt() * radius;
+        let mut mob = Mob::new(def.clone(), pos + Vec2::from_angle(angle) * distance);
+        mob.wave = alive.cloned();
+        monsters.push(Box::new(mob));
+    }
+}
+
+/// Reads the "waves" property of a spawner, which looks like "slime 3, mushroom 1; spitter 2".
+/// Waves are split by semicolons, each one lists monsters and how many of them to send
+pub fn parse_waves(waves: &str) -> Result<Vec<Wave>, MapErrorKind> {
+    let bad_waves = || MapErrorKind::BadWaves(waves.to_string());
+    let mut parsed = vec![];
+    for wave in waves.split(';') {
+        let mut monsters = vec![];
+        for group in wave.split(',') {
+            let mut words = group.split_whitespace();
+            let kind = words.next().ok_or_else(bad_waves)?;
+            let count = match words.next() {
+                Some(count) => count.parse::<u32>().map_err(|_| bad_waves())?,
+                None => 1,
+            };
+            if words.next().is_some() {
+                return Err(bad_waves());
+            }
+            monsters.push((kind.into(), count));
+        }
+        parsed.push(monsters);
     }
+    Ok(parsed)
 }
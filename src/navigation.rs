@@ -0,0 +1,163 @@
+use macroquad::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::logic::TILE;
+
+// Moving straight costs 10, diagonally about 10 * sqrt(2)
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+const EDGE: f32 = 1.;
+
+/// Which tiles of a map can be walked through, built once from Area::walls when the map loads
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    /// width and height are in tiles. A tile is blocked if any wall touches it at all
+    pub fn new(walls: &[Rect], width: usize, height: usize) -> Self {
+        let mut blocked = vec![false; width * height];
+        for wall in walls {
+            // Walls that only touch a tile's edge don't count, give or take some rounding
+            let left = ((wall.left() + EDGE) / TILE).floor().max(0.) as usize;
+            let top = ((wall.top() + EDGE) / TILE).floor().max(0.) as usize;
+            let right = (((wall.right() - EDGE) / TILE).ceil().max(0.) as usize).min(width);
+            let bottom = (((wall.bottom() - EDGE) / TILE).ceil().max(0.) as usize).min(height);
+            for y in top..bottom {
+                for x in left..right {
+                    blocked[y * width + x] = true;
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            blocked,
+        }
+    }
+
+    /// Anything outside of the map is blocked too
+    pub fn is_blocked(&self, cell: (i32, i32)) -> bool {
+        match self.index(cell) {
+            Some(index) => self.blocked[index],
+            None => true,
+        }
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    pub fn cell_of(point: Vec2) -> (i32, i32) {
+        (
+            (point.x / TILE).floor() as i32,
+            (point.y / TILE).floor() as i32,
+        )
+    }
+
+    pub fn center_of((x, y): (i32, i32)) -> Vec2 {
+        vec2((x as f32 + 0.5) * TILE, (y as f32 + 0.5) * TILE)
+    }
+
+    /// Whether a straight line between the two points stays out of blocked tiles
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        // A quarter of a tile at a time is fine enough to not skip over any corners that matter
+        let steps = (from.distance(to) / (TILE / 4.)).ceil().max(1.) as u32;
+        (0..=steps).all(|step| {
+            let point = from.lerp(to, step as f32 / steps as f32);
+            !self.is_blocked(Self::cell_of(point))
+        })
+    }
+
+    /// A* from one point to another, giving the center of every tile to walk through after the
+    /// starting one. The start is allowed to be blocked, since a monster pushed halfway into a
+    /// wall still has to find its way out. None if the goal can't be reached
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = Self::cell_of(from);
+        let goal = Self::cell_of(to);
+        let start_index = self.index(start)?;
+        let goal_index = self.index(goal)?;
+        if self.blocked[goal_index] {
+            return None;
+        }
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start_index] = 0;
+        open.push(Reverse((heuristic(start, goal), start_index)));
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            if index == goal_index {
+                return Some(self.walk_back(&came_from, start_index, goal_index));
+            }
+            let cell = ((index % self.width) as i32, (index / self.width) as i32);
+            for (next, step_cost) in self.neighbours(cell) {
+                let next_index = self.index(next).unwrap();
+                let new_cost = cost[index] + step_cost;
+                if new_cost >= cost[next_index] {
+                    continue;
+                }
+                cost[next_index] = new_cost;
+                came_from[next_index] = index;
+                open.push(Reverse((new_cost + heuristic(next, goal), next_index)));
+            }
+        }
+        None
+    }
+
+    fn neighbours(&self, (x, y): (i32, i32)) -> Vec<((i32, i32), u32)> {
+        let mut neighbours = vec![];
+        for (dx, dy) in [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ] {
+            let next = (x + dx, y + dy);
+            if self.is_blocked(next) {
+                continue;
+            }
+            if dx != 0 && dy != 0 {
+                // No squeezing between two walls that touch at a corner
+                if self.is_blocked((x + dx, y)) || self.is_blocked((x, y + dy)) {
+                    continue;
+                }
+                neighbours.push((next, DIAGONAL));
+            } else {
+                neighbours.push((next, STRAIGHT));
+            }
+        }
+        neighbours
+    }
+
+    fn walk_back(&self, came_from: &[usize], start: usize, goal: usize) -> Vec<Vec2> {
+        let mut path = vec![];
+        let mut index = goal;
+        while index != start {
+            let cell = ((index % self.width) as i32, (index / self.width) as i32);
+            path.push(Self::center_of(cell));
+            index = came_from[index];
+        }
+        path.reverse();
+        path
+    }
+}
+
+// Octile distance, which never overestimates with 8 way movement
+fn heuristic(a: (i32, i32), b: (i32, i32)) -> u32 {
+    let dx = a.0.abs_diff(b.0);
+    let dy = a.1.abs_diff(b.1);
+    STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+}
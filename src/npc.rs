@@ -1,39 +1,164 @@
+use crate::dialogue::Dialogue;
 use crate::logic::*;
 use crate::player::{angle_between, should_face, Orientation};
+use crate::spatial::Walls;
 use macroquad::experimental::animation::AnimatedSprite;
 use macroquad::prelude::*;
+use macroquad::rand::gen_range;
 use serde_json::Value;
 use std::fs::read_to_string;
 use std::path::PathBuf;
 use std::rc::Rc;
 
+const NPC_SPEED: f32 = 1.5 * TILE;
+// How long wandering NPCs stand around before picking somewhere else to go
+const WANDER_PAUSE: f32 = 3.;
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct NPC {
+    // The Tiled object id
+    pub id: u64,
     pub name: Rc<str>,
-    pub dialogs: Vec<Vec<char>>,
+    // The texture in res/, from the "sprite" property in Tiled or the name if there isn't one
+    pub sprite: Rc<str>,
+    pub dialogue: Dialogue,
     pub hitbox: Rect,
     pub anim: AnimatedSprite,
     pub is_talking: bool,
     pub facing: Orientation,
+    // Item ids for sale, from the "shop" property in Tiled. Empty if they don't sell anything
+    pub shop: Vec<Rc<str>>,
+    // The object in the Paths layer the "path" property points to, turned into movement once the
+    // whole map is loaded
+    pub path: Option<u64>,
+    pub movement: Movement,
+    pub moving: bool,
 }
 
-impl NPC {
-    pub fn new(name: &str, diag_path: &str, hitbox: Rect) -> Self {
-        // Because cross platform lol
-        let path: PathBuf = diag_path.replace("..", "assets").into();
-        let dialogs = make_dialog(path).unwrap();
+/// Where an NPC walks, the hitbox's center is what follows it
+#[derive(Clone, Debug)]
+pub enum Movement {
+    Still,
+    // Goes from point to point, then starts over from the first one
+    Patrol {
+        points: Vec<Vec2>,
+        next: usize,
+    },
+    // Walks to random spots in the region, waiting a bit at each one
+    Wander {
+        region: Rect,
+        target: Option<Vec2>,
+        pause: Timer,
+    },
+}
+
+/// An object in the Paths layer. Polylines are walked there and back, polygons in a loop, and
+/// rectangles are wandered around in
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub id: u64,
+    pub movement: Movement,
+}
+
+impl Movement {
+    /// Points are in world space. Open paths get walked back the same way instead of jumping
+    /// from the last point to the first
+    pub fn patrol(mut points: Vec<Vec2>, closed: bool) -> Self {
+        if !closed && points.len() > 2 {
+            let back: Vec<Vec2> = points[1..points.len() - 1].iter().rev().copied().collect();
+            points.extend(back);
+        }
+        Movement::Patrol { points, next: 0 }
+    }
+
+    pub fn wander(region: Rect) -> Self {
+        Movement::Wander {
+            region,
+            target: None,
+            pause: Timer::new(WANDER_PAUSE),
+        }
+    }
+}
 
+impl NPC {
+    pub fn new(name: &str, dialogue: Dialogue, hitbox: Rect) -> Self {
         let anim = npc_anim();
 
         NPC {
+            id: 0,
             name: name.into(),
-            dialogs,
+            sprite: name.into(),
+            dialogue,
             anim,
             hitbox,
             is_talking: false,
             facing: Orientation::Down,
+            shop: vec![],
+            path: None,
+            movement: Movement::Still,
+            moving: false,
         }
     }
 
+    /// Stands still while talking, or when the next step would walk into something
+    pub fn tick(&mut self, obstacles: &[Rect], walls: &Walls, dt: f32) {
+        self.moving = false;
+        if self.is_talking {
+            return;
+        }
+        let mut center = self.hitbox.center();
+        let target = match &mut self.movement {
+            Movement::Still => return,
+            Movement::Patrol { points, next } => {
+                if center.distance(points[*next]) < NPC_SPEED * dt {
+                    // Snapping keeps it from jittering around a path with a single point
+                    center = points[*next];
+                    self.hitbox = self.hitbox.offset(center - self.hitbox.center());
+                    *next = (*next + 1) % points.len();
+                }
+                points[*next]
+            }
+            Movement::Wander {
+                region,
+                target,
+                pause,
+            } => match target {
+                Some(spot) if center.distance(*spot) < NPC_SPEED * dt => {
+                    *target = None;
+                    *pause = Timer::new(WANDER_PAUSE);
+                    return;
+                }
+                Some(spot) => *spot,
+                None => {
+                    pause.tick(dt);
+                    if pause.is_done() {
+                        let x = gen_range(region.left(), region.right());
+                        let y = gen_range(region.top(), region.bottom());
+                        *target = Some(vec2(x, y));
+                    }
+                    return;
+                }
+            },
+        };
+        let step = (target - center).normalize_or_zero() * NPC_SPEED * dt;
+        if step == Vec2::ZERO {
+            return;
+        }
+        let next = self.hitbox.offset(step);
+        let mut blockers = obstacles.iter().chain(walls.near(next));
+        if blockers.any(|rect| rect.overlaps(&next)) {
+            // Wanderers give up on that spot, patrols just wait for the way to clear
+            if let Movement::Wander { target, pause, .. } = &mut self.movement {
+                *target = None;
+                *pause = Timer::new(WANDER_PAUSE);
+            }
+            return;
+        }
+        self.hitbox = next;
+        self.moving = true;
+        self.facing = should_face(angle_between(center, target));
+    }
+
     pub fn draw(&self, texture: &Texture2D) {
         let dest_size = Some(self.anim.frame().dest_size * SCALE_FACTOR);
         let source = Some(self.anim.frame().source_rect);
@@ -63,8 +188,7 @@ impl NPC {
     }
 
     pub fn face(&mut self, pos: Vec2) {
-        let npc_pos = vec2(self.hitbox.x, self.hitbox.y);
-        let angle = angle_between(npc_pos, pos);
+        let angle = angle_between(self.hitbox.center(), pos);
         self.facing = should_face(angle);
     }
 
@@ -75,6 +199,11 @@ impl NPC {
             Orientation::Left => self.anim.set_animation(1),
             Orientation::Right => self.anim.set_animation(3),
         }
+        // The sheet only has walking frames, so standing is just the first one
+        self.anim.playing = self.moving;
+        if !self.moving {
+            self.anim.set_frame(0);
+        }
     }
 }
 
@@ -92,20 +221,14 @@ fn npc_anim() -> AnimatedSprite {
     )
 }
 
-fn make_dialog(path: PathBuf) -> Option<Vec<Vec<char>>> {
-    let mut dialog = vec![];
-
-    let json_string = read_to_string(path).unwrap();
-    let parsed: Value = serde_json::from_str(&json_string).unwrap();
-    let arr = parsed["dialog"].as_array()?;
-
-    for item in arr {
-        let mut chars: Vec<char> = item.as_str()?.chars().collect();
-        chars.push(' ');
-        dialog.push(chars)
-    }
+/// Takes the path as written in the map file, which is relative to assets/maps
+pub fn load_dialog(diag_path: &str) -> Result<Dialogue, String> {
+    // Because cross platform lol
+    let path: PathBuf = diag_path.replace("..", "assets").into();
 
-    Some(dialog)
+    let json_string = read_to_string(path).map_err(|err| err.to_string())?;
+    let parsed: Value = serde_json::from_str(&json_string).map_err(|err| err.to_string())?;
+    Dialogue::from_json(&parsed)
 }
 
 pub fn overlay_pos(rect: Rect) -> Vec2 {
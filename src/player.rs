@@ -1,8 +1,11 @@
 use crate::camera::Utils;
+use crate::effects::{Buff, BuffKind};
+use crate::input::InputFrame;
 use crate::logic::*;
 use crate::map::Projectile;
+use crate::spatial::Walls;
 use crate::ui::inventory::Inventory;
-use crate::ui::items::ItemID;
+use crate::ui::items::ItemType;
 use crate::weapons::Weapon;
 use crate::Rc;
 use macroquad::experimental::animation::*;
@@ -41,6 +44,10 @@ pub struct Player {
     pub inventory: Inventory,
     pub combo: u8,
     pub spawn_loc: SpawnLocation,
+    // From consumables, see effects.rs
+    pub buffs: Vec<Buff>,
+    // Spent and earned in shops
+    pub coins: u32,
 }
 
 #[derive(Clone)]
@@ -55,6 +62,9 @@ pub struct Props {
     pub health: f32,
     pub animation: AnimatedSprite,
     pub pos: Vec2,
+    // Where pos was before the last tick and where it should be drawn, see Props::interpolate
+    pub prev_pos: Vec2,
+    pub render_pos: Vec2,
     pub should_despawn: bool,
     pub flip_sprite: bool,
 }
@@ -77,11 +87,17 @@ pub trait Collidable {
     fn hitbox(&self) -> Rect;
     fn mut_pos(&mut self) -> &mut Vec2;
     fn pos(&self) -> Vec2;
-    fn wall_collsion(&mut self, walls: &[Rect]) {
+    fn wall_collsion(&mut self, walls: &Walls) {
+        let near: Vec<Rect> = walls.near(self.hitbox()).copied().collect();
+        self.collide(&near)
+    }
+
+    /// Pushes the hitbox out of every rect it overlaps
+    fn collide(&mut self, rects: &[Rect]) {
         let hitbox = self.hitbox();
         let pos = self.mut_pos();
 
-        for wall in walls {
+        for wall in rects {
             if let Some(rect) = wall.intersect(hitbox) {
                 if rect.w < rect.h {
                     if hitbox.right() > wall.right() {
@@ -89,12 +105,10 @@ pub trait Collidable {
                     } else {
                         pos.x -= rect.w
                     }
+                } else if hitbox.bottom() > wall.bottom() {
+                    pos.y += rect.h
                 } else {
-                    if hitbox.bottom() > wall.bottom() {
-                        pos.y += rect.h
-                    } else {
-                        pos.y -= rect.h
-                    }
+                    pos.y -= rect.h
                 }
             }
         }
@@ -127,13 +141,34 @@ impl Props {
             health: heath,
             animation,
             pos,
+            prev_pos: pos,
+            render_pos: pos,
             should_despawn: false,
             flip_sprite: false,
         }
     }
 
-    pub fn new_pos(&mut self) {
-        let delta_time = get_frame_time();
+    /// Called at the start of every tick so the renderer knows where we came from
+    pub fn save_pos(&mut self) {
+        self.prev_pos = self.pos
+    }
+
+    /// Stops the entity from being drawn sliding across the map after being teleported
+    pub fn snap(&mut self) {
+        self.prev_pos = self.pos;
+        self.render_pos = self.pos;
+    }
+
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.render_pos = self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    // Shifts a point tied to pos to where it would be drawn
+    pub fn to_render(&self, point: Vec2) -> Vec2 {
+        point + self.render_pos - self.pos
+    }
+
+    pub fn new_pos(&mut self, delta_time: f32) {
         self.pos.x += self.velocity.x * delta_time;
         self.pos.y += self.velocity.y * delta_time;
 
@@ -161,11 +196,13 @@ impl Player {
             state: PlayerState::Normal,
             invul_time: Timer::new(INVUL_TIME),
             props: Props::from(pos, PLAYER_HEALTH, animation),
-            held_weapon: Weapon::rusty_sword(),
+            held_weapon: Weapon::default(),
             facing: Orientation::Down,
             inventory: Inventory::empty(),
             spawn_loc: SpawnLocation::new(pos, map),
             combo: 0,
+            buffs: vec![],
+            coins: 0,
         }
     }
 
@@ -187,41 +224,44 @@ impl Player {
         Rect::new(x, y, w, h)
     }
 
-    pub fn new_pos(&mut self) {
+    pub fn new_pos(&mut self, input: &InputFrame) {
         if self.state != PlayerState::Normal {
             return;
         }
         let mut movement_vector: Vec2 = vec2(0., 0.);
-        if is_key_down(KeyCode::W) {
+        if input.key_down(KeyCode::W) {
             movement_vector.y += -1.;
             self.facing = Orientation::Up;
         }
-        if is_key_down(KeyCode::S) {
+        if input.key_down(KeyCode::S) {
             movement_vector.y += 1.;
             self.facing = Orientation::Down;
         }
-        if is_key_down(KeyCode::A) {
+        if input.key_down(KeyCode::A) {
             movement_vector.x += -1.;
             self.facing = Orientation::Left;
         }
-        if is_key_down(KeyCode::D) {
+        if input.key_down(KeyCode::D) {
             movement_vector.x += 1.;
             self.facing = Orientation::Right;
         }
         if movement_vector != Vec2::ZERO {
-            movement_vector = movement_vector.normalize() * PLAYER_VELOCITY;
+            movement_vector =
+                movement_vector.normalize() * PLAYER_VELOCITY * self.multiplier(BuffKind::Speed);
         }
         self.props.velocity += movement_vector;
 
-        self.props.new_pos();
+        self.props.new_pos(input.dt);
     }
 
-    pub fn tick(&mut self, mouse_pos: Vec2) {
-        self.invul_time.tick();
-        self.state_management();
+    pub fn tick(&mut self, input: &InputFrame) {
+        let mouse_pos = input.mouse_pos;
+        self.invul_time.tick(input.dt);
+        self.tick_buffs(input.dt);
+        self.state_management(input);
 
         if self.state == PlayerState::Normal {
-            self.new_pos();
+            self.new_pos(input);
             self.change_anim(self.props.is_moving());
             return;
         }
@@ -229,26 +269,36 @@ impl Player {
             let angle = angle_between(self.pos(), mouse_pos);
             self.facing = should_face(angle);
             self.change_anim(false);
-            self.state = PlayerState::Throwing(time + get_frame_time());
+            self.state = PlayerState::Throwing(time + input.dt);
             return;
         }
         if let PlayerState::Attacking(attack) = self.state {
             let prog = attack.timer.progress();
             if prog > 0.5 {
-                self.props.new_pos();
+                self.props.new_pos(input.dt);
             }
         }
     }
 
+    /// Takes damage unless still invulnerable from the last hit, true if it landed
+    pub fn hurt(&mut self, damage: f32) -> bool {
+        if !self.invul_time.is_done() {
+            return false;
+        }
+        self.props.health -= damage;
+        self.invul_time.repeat();
+        true
+    }
+
     pub fn current_projectile(&self, mouse_pos: Vec2) -> Projectile {
         let angle = angle_between(self.pos(), mouse_pos);
         let vec = Vec2::from_angle(angle);
         Projectile::new(self.projectile_pos(mouse_pos), vec)
     }
 
-    fn state_management(&mut self) {
+    fn state_management(&mut self, input: &InputFrame) {
         if let PlayerState::Attacking(mut attack) = self.state {
-            attack.timer.tick();
+            attack.timer.tick(input.dt);
             self.state = match attack.timer.is_done() {
                 true => PlayerState::Normal,
                 false => PlayerState::Attacking(attack),
@@ -256,10 +306,10 @@ impl Player {
             self.change_anim(false);
             return;
         }
-        if is_mouse_button_pressed(MouseButton::Right) {
+        if input.mouse_pressed(MouseButton::Right) {
             self.state = PlayerState::Throwing(0.)
         }
-        if is_mouse_button_released(MouseButton::Right) {
+        if input.mouse_released(MouseButton::Right) {
             self.state = PlayerState::Normal
         }
     }
@@ -330,7 +380,7 @@ impl Player {
             ..Default::default()
         };
 
-        let pos = self.pos();
+        let pos = self.render_pos();
         let center = vec2(
             pos.x + (20. * PIXEL * rotation.cos()),
             pos.y + (20. * PIXEL * rotation.sin()),
@@ -344,7 +394,7 @@ impl Player {
         if progress < 0.5 {
             return;
         }
-        let flip = self.combo % 2 == 0;
+        let flip = self.combo.is_multiple_of(2);
         let extra = flip as u8 as f32 * (2. / 3. * PI);
         let rotation = -angle_between(self.pos(), mouse_pos) + 1. * PI / 6. + extra;
         let source = Some(source_rect(progress));
@@ -357,7 +407,7 @@ impl Player {
             ..Default::default()
         };
 
-        let player_pos = self.pos();
+        let player_pos = self.render_pos();
 
         draw_texture_ex(
             texture,
@@ -370,10 +420,8 @@ impl Player {
 
     pub fn draw(&self, texture: &Texture2D) {
         // Basicly this makes the player flash after it's been hurt
-        if !self.invul_time.is_done() {
-            if rand() % 3 == 0 {
-                return;
-            }
+        if !self.invul_time.is_done() && rand().is_multiple_of(3) {
+            return;
         }
         let dest_size = Some(self.props.animation.frame().dest_size * SCALE_FACTOR);
         let draw_param = DrawTextureParams {
@@ -383,8 +431,8 @@ impl Player {
         };
         draw_texture_ex(
             texture,
-            self.props.pos.x,
-            self.props.pos.y,
+            self.props.render_pos.x,
+            self.props.render_pos.y,
             WHITE,
             draw_param,
         );
@@ -396,14 +444,14 @@ impl Player {
         let front = vec2(pos.x - 6. * PIXEL, pos.y - 14. * PIXEL);
         let back = vec2(pos.x - 28. * PIXEL, pos.y - 14. * PIXEL);
         if (angle < PI / 2. && angle > 0.) || (angle > -PI / 2. && angle < 0.) {
-            return front;
+            front
         } else {
-            return back;
+            back
         }
     }
 
     pub fn draw_held_proj(&self, texture: &Texture2D, mouse_pos: Vec2) {
-        let pos = self.projectile_pos(mouse_pos);
+        let pos = self.props.to_render(self.projectile_pos(mouse_pos));
         let source = Some(Rect::new(TILE_SIZE * 6., TILE_SIZE, TILE_SIZE, TILE_SIZE));
         let dest_size = Some(vec2(TILE, TILE));
         let params = DrawTextureParams {
@@ -415,7 +463,7 @@ impl Player {
     }
 
     pub fn draw_throw_indicator(&self, mouse_pos: Vec2, texture: &Texture2D, time: f32) {
-        let pos = self.pos();
+        let pos = self.render_pos();
         let vec = (mouse_pos - pos).normalize() * 500.;
         let pos = vec2(pos.x + vec.x - TILE / 2., pos.y + vec.y - TILE / 2.);
 
@@ -432,26 +480,27 @@ impl Player {
     }
 
     fn weapon_texture(&self) -> Option<Rect> {
-        let inv = &self.inventory.content[12];
-        if let None = inv {
-            return None;
-        }
-        return match inv.as_ref().unwrap().kind {
-            ItemID::RustySword => Some(Rect::new(0., 3. * TILE_SIZE, TILE_SIZE, TILE_SIZE)),
-            ItemID::BlackSword => Some(Rect::new(TILE_SIZE, 3. * TILE_SIZE, TILE_SIZE, TILE_SIZE)),
+        let inv = self.inventory.content[12].as_ref()?;
+        match inv.def.class {
+            ItemType::Weapon => Some(inv.def.icon),
             _ => None,
-        };
+        }
+    }
+
+    /// Same as pos() but for drawing
+    pub fn render_pos(&self) -> Vec2 {
+        self.props.to_render(self.pos())
     }
 
     pub fn search_box(&self) -> Rect {
         let pos = self.pos();
         let rect = Rect::new(0., 0., TILE, TILE);
-        return match self.facing {
+        match self.facing {
             Orientation::Up => rect.center_on(vec2(pos.x, pos.y - TILE / 2.)),
             Orientation::Left => rect.center_on(vec2(pos.x - TILE / 2., pos.y)),
             Orientation::Down => rect.center_on(vec2(pos.x, pos.y + TILE / 2.)),
             Orientation::Right => rect.center_on(vec2(pos.x + TILE / 2., pos.y)),
-        };
+        }
     }
 }
 
@@ -0,0 +1,318 @@
+use macroquad::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::rc::Rc;
+
+use crate::logic::*;
+use crate::map::{MapErrorKind, MapLoadError};
+use crate::monsters::definition::MonsterDefs;
+use crate::player::Collidable;
+use crate::ui::inventory::Inventory;
+use crate::ui::items::{Item, ItemEntity, ItemRegistry};
+
+/// Every quest in assets/quests, by file name
+pub type QuestDefs = HashMap<Rc<str>, Rc<QuestDef>>;
+
+#[derive(Debug)]
+pub struct QuestDef {
+    pub id: Rc<str>,
+    pub name: String,
+    pub description: String,
+    pub objectives: Vec<Objective>,
+    // Item ids and how many of each
+    pub rewards: Vec<(Rc<str>, u8)>,
+}
+
+#[derive(Debug)]
+pub struct Objective {
+    pub goal: Goal,
+    pub count: u32,
+    // Shown in the quest log instead of the made up one
+    pub text: Option<String>,
+}
+
+/// Also what happened in the game, which moves along every objective with the same goal
+#[derive(Clone, Debug, PartialEq)]
+pub enum Goal {
+    // Monster names as in assets/monsters
+    Kill(Rc<str>),
+    // Counts whatever is in the inventory, wherever it came from
+    Collect(Rc<str>),
+    // NPC names as in Tiled
+    Talk(Rc<str>),
+    Reach(Rc<str>),
+}
+
+/// A quest the player has taken, along with how far each objective is
+#[derive(Clone, Debug)]
+pub struct Quest {
+    pub def: Rc<QuestDef>,
+    pub progress: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct QuestLog {
+    pub active: Vec<Quest>,
+    // Turned in, by id
+    pub finished: Vec<Rc<str>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuestState {
+    New,
+    Active,
+    // Every objective is done but it hasn't been turned in
+    Ready,
+    Finished,
+}
+
+impl QuestDef {
+    pub fn from_json(id: &str, json: &Value) -> Result<Self, MapErrorKind> {
+        let text = |field: &'static str| {
+            json[field]
+                .as_str()
+                .map(|text| text.to_string())
+                .ok_or(MapErrorKind::Field(field))
+        };
+        let objectives = json["objectives"]
+            .as_array()
+            .filter(|objectives| !objectives.is_empty())
+            .ok_or(MapErrorKind::Field("objectives"))?
+            .iter()
+            .map(Objective::from_json)
+            .collect::<Result<_, _>>()?;
+        let mut rewards = vec![];
+        for reward in json["rewards"].as_array().into_iter().flatten() {
+            let item = reward["item"]
+                .as_str()
+                .ok_or(MapErrorKind::Field("rewards"))?;
+            let count = reward["count"].as_u64().unwrap_or(1).min(u8::MAX as u64) as u8;
+            rewards.push((item.into(), count))
+        }
+        Ok(Self {
+            id: id.into(),
+            name: text("name")?,
+            description: text("description")?,
+            objectives,
+            rewards,
+        })
+    }
+}
+
+impl Objective {
+    // One of { "kill": "slime", "count": 5 }, { "collect": "mushroom", "count": 3 },
+    // { "talk": "fisherman" } or { "reach": "Lake" }
+    fn from_json(json: &Value) -> Result<Self, MapErrorKind> {
+        let name = |field: &str| json[field].as_str().map(|name| name.into());
+        let goal = if let Some(monster) = name("kill") {
+            Goal::Kill(monster)
+        } else if let Some(item) = name("collect") {
+            Goal::Collect(item)
+        } else if let Some(npc) = name("talk") {
+            Goal::Talk(npc)
+        } else if let Some(map) = name("reach") {
+            Goal::Reach(map)
+        } else {
+            return Err(MapErrorKind::Field("objectives"));
+        };
+        Ok(Self {
+            goal,
+            count: json["count"].as_u64().unwrap_or(1) as u32,
+            text: json["text"].as_str().map(|text| text.to_string()),
+        })
+    }
+}
+
+impl Objective {
+    /// What the quest log shows for it, made up from the goal unless the quest has its own text
+    pub fn label(&self) -> String {
+        if let Some(text) = &self.text {
+            return text.clone();
+        }
+        match &self.goal {
+            Goal::Kill(monster) => format!("Hạ {monster}"),
+            Goal::Collect(item) => format!("Nhặt {item}"),
+            Goal::Talk(npc) => format!("Nói chuyện với {npc}"),
+            Goal::Reach(map) => format!("Đến {map}"),
+        }
+    }
+}
+
+impl Quest {
+    pub fn is_complete(&self) -> bool {
+        let objectives = self.def.objectives.iter();
+        objectives
+            .zip(&self.progress)
+            .all(|(objective, progress)| *progress >= objective.count)
+    }
+}
+
+impl QuestLog {
+    pub fn state(&self, id: &str) -> QuestState {
+        if self.finished.iter().any(|finished| &**finished == id) {
+            return QuestState::Finished;
+        }
+        match self.active.iter().find(|quest| &*quest.def.id == id) {
+            Some(quest) if quest.is_complete() => QuestState::Ready,
+            Some(_) => QuestState::Active,
+            None => QuestState::New,
+        }
+    }
+
+    /// Sets every collect objective to how many of the item the player has right now
+    pub fn count_items(&mut self, inventory: &Inventory) {
+        for quest in self.active.iter_mut() {
+            let objectives = quest.def.objectives.iter();
+            for (objective, progress) in objectives.zip(quest.progress.iter_mut()) {
+                if let Goal::Collect(item) = &objective.goal {
+                    *progress = inventory.count(item).min(objective.count)
+                }
+            }
+        }
+    }
+
+    /// Moves along every active objective after that
+    pub fn progress(&mut self, goal: &Goal, amount: u32) {
+        for quest in self.active.iter_mut() {
+            let objectives = quest.def.objectives.iter();
+            for (objective, progress) in objectives.zip(quest.progress.iter_mut()) {
+                if objective.goal == *goal {
+                    *progress = (*progress + amount).min(objective.count)
+                }
+            }
+        }
+    }
+}
+
+impl Game {
+    /// Nothing happens if the quest was already taken
+    pub fn start_quest(&mut self, id: &str) {
+        if self.quests.state(id) != QuestState::New {
+            return;
+        }
+        let def = match self.quest_defs.get(id) {
+            Some(def) => def.clone(),
+            None => {
+                warn!("unknown quest {id}");
+                return;
+            }
+        };
+        let progress = vec![0; def.objectives.len()];
+        self.quests.active.push(Quest { def, progress });
+        // What the player already has counts too
+        self.quests.count_items(&self.player.inventory);
+    }
+
+    /// Takes the collected items and hands out the rewards, but only once every objective is done
+    pub fn finish_quest(&mut self, id: &str) {
+        // The items could have been used or thrown around since the last check
+        self.quests.count_items(&self.player.inventory);
+        if self.quests.state(id) != QuestState::Ready {
+            return;
+        }
+        let index = self
+            .quests
+            .active
+            .iter()
+            .position(|quest| &*quest.def.id == id)
+            .unwrap();
+        let quest = self.quests.active.remove(index);
+        for objective in &quest.def.objectives {
+            if let Goal::Collect(item) = &objective.goal {
+                self.player.inventory.remove(item, objective.count);
+            }
+        }
+        self.quests.finished.push(quest.def.id.clone());
+
+        let pos = self.player.hitbox().center();
+        let current_map = self.maps.get_mut(&self.current_map).unwrap();
+        for (item, count) in &quest.def.rewards {
+            let item = match Item::from_key(&self.item_defs, item, *count) {
+                Some(item) => item,
+                None => {
+                    warn!("unknown item {item} in the rewards of {id}");
+                    continue;
+                }
+            };
+            // Whatever doesn't fit ends up at the player's feet
+            if let Some(rest) = self.player.inventory.append(item) {
+                current_map.items.push(ItemEntity::new(rest, pos))
+            }
+        }
+    }
+}
+
+/// Reads every quest, reporting all the broken ones at once
+pub fn load_quests() -> Result<QuestDefs, Vec<MapLoadError>> {
+    let mut quests = QuestDefs::new();
+    let mut errors = vec![];
+
+    for path in get_path("assets/quests/", ".json").map_err(|err| vec![err])? {
+        let file = path.to_string_lossy();
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let result = read_to_string(&path)
+            .map_err(|err| MapErrorKind::Io(err.to_string()))
+            .and_then(|json| {
+                serde_json::from_str(&json).map_err(|err| MapErrorKind::Json(err.to_string()))
+            })
+            .and_then(|json: Value| QuestDef::from_json(name, &json));
+        match result {
+            Ok(quest) => {
+                quests.insert(name.into(), Rc::new(quest));
+            }
+            Err(kind) => errors.push(MapLoadError::new(kind).in_file(&file)),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(quests),
+        false => Err(errors),
+    }
+}
+
+/// Every item, monster or map a quest names that doesn't exist. NPC names are checked by
+/// validate-maps since they live in the maps
+pub fn unknown_names(
+    quests: &QuestDefs,
+    items: &ItemRegistry,
+    monsters: &MonsterDefs,
+    maps: &[&str],
+) -> Vec<MapLoadError> {
+    let mut errors = vec![];
+    for quest in quests.values() {
+        let file = format!("assets/quests/{}.json", quest.id);
+        let mut check = |known: bool, kind: MapErrorKind| {
+            if !known {
+                errors.push(MapLoadError::new(kind).in_file(&file))
+            }
+        };
+        for (item, _) in &quest.rewards {
+            check(
+                items.contains_key(item),
+                MapErrorKind::UnknownItem(item.to_string()),
+            )
+        }
+        for objective in &quest.objectives {
+            match &objective.goal {
+                Goal::Kill(monster) => check(
+                    monsters.contains_key(monster),
+                    MapErrorKind::UnknownMob(monster.to_string()),
+                ),
+                Goal::Collect(item) => check(
+                    items.contains_key(item),
+                    MapErrorKind::UnknownItem(item.to_string()),
+                ),
+                Goal::Reach(map) => check(
+                    maps.contains(&&**map),
+                    MapErrorKind::UnknownMap(map.to_string()),
+                ),
+                Goal::Talk(_) => (),
+            }
+        }
+    }
+    errors
+}
@@ -0,0 +1,340 @@
+use macroquad::prelude::*;
+use serde_json::{json, Value};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::flags::Flags;
+use crate::logic::*;
+use crate::monsters::boss::Fight;
+use crate::monsters::spawner::{SpawnMode, WaveState};
+use crate::player::PlayerState;
+use crate::quests::{Quest, QuestLog};
+use crate::ui::inventory::{get_weapon, Inventory};
+use crate::ui::items::{Item, ItemEntity, ItemRegistry};
+use crate::weapons::Weapon;
+
+pub const SAVE_SLOTS: usize = 3;
+const SAVE_DIR: &str = "saves/";
+const SAVE_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    // The file is valid JSON but not something we wrote
+    Invalid(String),
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Json(err)
+    }
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save file: {err}"),
+            SaveError::Json(err) => write!(f, "save file is not valid JSON: {err}"),
+            SaveError::Invalid(why) => write!(f, "save file is invalid: {why}"),
+        }
+    }
+}
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(format!("{SAVE_DIR}slot{}.json", slot + 1))
+}
+
+/// The map the save in this slot was made on, None if the slot is empty or unreadable
+pub fn slot_summary(slot: usize) -> Option<String> {
+    let json_string = read_to_string(slot_path(slot)).ok()?;
+    let parsed: Value = serde_json::from_str(&json_string).ok()?;
+    Some(parsed["current_map"].as_str()?.to_string())
+}
+
+impl Game {
+    pub fn save_to_slot(&self, slot: usize) -> Result<(), SaveError> {
+        create_dir_all(SAVE_DIR)?;
+        let json_string = serde_json::to_string_pretty(&self.to_save())?;
+        write(slot_path(slot), json_string)?;
+        Ok(())
+    }
+
+    pub fn load_from_slot(&mut self, slot: usize) -> Result<(), SaveError> {
+        let json_string = read_to_string(slot_path(slot))?;
+        let parsed: Value = serde_json::from_str(&json_string)?;
+        self.apply_save(&parsed)
+    }
+
+    pub fn to_save(&self) -> Value {
+        let player = &self.player;
+
+        let inventory: Vec<Value> = player.inventory.content.iter().map(item_to_json).collect();
+        let holding = item_to_json(&player.inventory.holding().cloned());
+
+        let mut maps = serde_json::Map::new();
+        for (name, area) in self.maps.iter() {
+            let opened: Vec<u64> = area
+                .interactables
+                .iter()
+                .filter(|interactable| interactable.is_opened())
+                .map(|interactable| interactable.id())
+                .collect();
+            let defeated: Vec<u64> = area
+                .bosses
+                .iter()
+                .filter(|arena| arena.fight == Fight::Won)
+                .map(|arena| arena.id)
+                .collect();
+            let cleared: Vec<u64> = area
+                .spawners
+                .iter()
+                .filter_map(|spawner| match &spawner.mode {
+                    SpawnMode::Waves(waves) if waves.state == WaveState::Cleared => Some(waves.id),
+                    _ => None,
+                })
+                .collect();
+            // Chest content and loot that hasn't been picked up yet
+            let items: Vec<Value> = area
+                .items
+                .iter()
+                .map(|entity| {
+                    let mut item = item_to_json(&Some(entity.item.clone()));
+                    item["pos"] = json!([entity.hitbox.x, entity.hitbox.y]);
+                    item
+                })
+                .collect();
+            maps.insert(
+                name.to_string(),
+                json!({
+                    "opened_chests": opened,
+                    "defeated_bosses": defeated,
+                    "cleared_waves": cleared,
+                    "items": items,
+                }),
+            );
+        }
+
+        let flags: serde_json::Map<String, Value> = self
+            .flags
+            .values
+            .iter()
+            .map(|(name, value)| (name.to_string(), json!(value)))
+            .collect();
+        let active: serde_json::Map<String, Value> = self
+            .quests
+            .active
+            .iter()
+            .map(|quest| (quest.def.id.to_string(), json!(quest.progress)))
+            .collect();
+        let finished: Vec<&str> = self.quests.finished.iter().map(|id| &**id).collect();
+
+        json!({
+            "version": SAVE_VERSION,
+            "current_map": &*self.current_map,
+            "player": {
+                "pos": [player.props.pos.x, player.props.pos.y],
+                "health": player.props.health,
+                "coins": player.coins,
+                "spawn": {
+                    "map": &*player.spawn_loc.map,
+                    "pos": [player.spawn_loc.location.x, player.spawn_loc.location.y],
+                },
+                "inventory": inventory,
+                "holding": holding,
+            },
+            "maps": maps,
+            "flags": flags,
+            "quests": {
+                "active": active,
+                "finished": finished,
+            },
+        })
+    }
+
+    /// Nothing is changed if the save turns out to be invalid
+    pub fn apply_save(&mut self, save: &Value) -> Result<(), SaveError> {
+        let version = save["version"].as_u64().ok_or(invalid("missing version"))?;
+        if version > SAVE_VERSION {
+            return Err(invalid("made by a newer version of the game"));
+        }
+        let current_map = self.map_name(&save["current_map"])?;
+
+        let player = &save["player"];
+        let pos = read_vec2(&player["pos"]).ok_or(invalid("bad player position"))?;
+        let health = player["health"]
+            .as_f64()
+            .ok_or(invalid("bad player health"))? as f32;
+        // Older saves are from before there was money
+        let coins = match &player["coins"] {
+            Value::Null => 0,
+            coins => coins.as_u64().ok_or(invalid("bad coin count"))? as u32,
+        };
+        let spawn_map = self.map_name(&player["spawn"]["map"])?;
+        let spawn_pos = read_vec2(&player["spawn"]["pos"]).ok_or(invalid("bad spawn position"))?;
+        let content = player["inventory"]
+            .as_array()
+            .ok_or(invalid("missing inventory"))?;
+
+        let mut inventory = Inventory::empty();
+        for (index, slot) in content.iter().enumerate().take(inventory.content.len()) {
+            inventory.content[index] = item_from_json(slot, &self.item_defs);
+        }
+        inventory.set_holding(item_from_json(&player["holding"], &self.item_defs));
+
+        // Missing from older saves, which just means nothing has happened yet
+        let mut flags = Flags::default();
+        for (name, value) in save["flags"].as_object().into_iter().flatten() {
+            let value = value
+                .as_i64()
+                .ok_or(invalid("flags have to be whole numbers"))?;
+            flags.set(name, value);
+        }
+
+        // Same for quests. Ones that don't exist anymore are dropped
+        let mut quests = QuestLog::default();
+        for (id, progress) in save["quests"]["active"].as_object().into_iter().flatten() {
+            let def = match self.quest_defs.get(id.as_str()) {
+                Some(def) => def.clone(),
+                None => continue,
+            };
+            let progress = progress
+                .as_array()
+                .ok_or(invalid("quest progress has to be a list"))?;
+            let progress = (0..def.objectives.len())
+                .map(|index| progress.get(index).and_then(Value::as_u64).unwrap_or(0) as u32)
+                .collect();
+            quests.active.push(Quest { def, progress });
+        }
+        for id in save["quests"]["finished"].as_array().into_iter().flatten() {
+            let id = id.as_str().ok_or(invalid("quest ids have to be strings"))?;
+            if let Some((id, _)) = self.quest_defs.get_key_value(id) {
+                quests.finished.push(id.clone());
+            }
+        }
+
+        for (name, area) in self.maps.iter_mut() {
+            let opened: Vec<u64> = save["maps"][&**name]["opened_chests"]
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|id| id.as_u64()).collect())
+                .unwrap_or_default();
+            for interactable in area.interactables.iter_mut() {
+                interactable.set_opened(opened.contains(&interactable.id()));
+            }
+            // Older saves don't have this, so every boss is still alive in them
+            let defeated: Vec<u64> = save["maps"][&**name]["defeated_bosses"]
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|id| id.as_u64()).collect())
+                .unwrap_or_default();
+            for arena in area.bosses.iter_mut() {
+                arena.fight = match defeated.contains(&arena.id) {
+                    true => Fight::Won,
+                    false => Fight::Waiting,
+                };
+            }
+            let cleared: Vec<u64> = save["maps"][&**name]["cleared_waves"]
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|id| id.as_u64()).collect())
+                .unwrap_or_default();
+            for spawner in area.spawners.iter_mut() {
+                if let SpawnMode::Waves(waves) = &mut spawner.mode {
+                    // Half done waves start over, the monsters from them are gone anyway
+                    waves.state = match cleared.contains(&waves.id) {
+                        true => WaveState::Cleared,
+                        false => WaveState::Waiting,
+                    };
+                }
+            }
+            area.items = save["maps"][&**name]["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|entity| {
+                    let pos = read_vec2(&entity["pos"])?;
+                    Some(ItemEntity::new(
+                        item_from_json(entity, &self.item_defs)?,
+                        pos,
+                    ))
+                })
+                .collect();
+            // None of these are saved, so don't keep the ones from before loading around
+            area.enemies.clear();
+            area.projectiles.clear();
+            area.update_locks();
+        }
+
+        let player = &mut self.player;
+        player.held_weapon = match &inventory.content[12] {
+            Some(item) => get_weapon(item),
+            None => Weapon::default(),
+        };
+        player.inventory = inventory;
+        player.props.pos = pos;
+        player.props.velocity = vec2(0., 0.);
+        player.props.health = health;
+        player.coins = coins;
+        // Buffs wear off in seconds, not worth saving
+        player.buffs.clear();
+        player.props.snap();
+        player.spawn_loc.map = spawn_map;
+        player.spawn_loc.location = spawn_pos;
+        player.state = PlayerState::Normal;
+        player.invul_time = Timer::new(player.invul_time.duration);
+        self.current_map = current_map;
+        self.flags = flags;
+        self.quests = quests;
+        self.tasks.clear();
+        self.state = GameState::Normal;
+        Ok(())
+    }
+
+    fn map_name(&self, name: &Value) -> Result<Rc<str>, SaveError> {
+        let name = name.as_str().ok_or(invalid("missing map name"))?;
+        match self.maps.get_key_value(name) {
+            Some((name, _)) => Ok(name.clone()),
+            None => Err(SaveError::Invalid(format!("map {name} does not exist"))),
+        }
+    }
+}
+
+fn invalid(why: &str) -> SaveError {
+    SaveError::Invalid(why.to_string())
+}
+
+fn read_vec2(value: &Value) -> Option<Vec2> {
+    let x = value.get(0)?.as_f64()? as f32;
+    let y = value.get(1)?.as_f64()? as f32;
+    Some(vec2(x, y))
+}
+
+fn item_to_json(slot: &Option<Item>) -> Value {
+    match slot {
+        Some(item) => json!({ "item": item.id(), "count": item.count }),
+        None => Value::Null,
+    }
+}
+
+fn item_from_json(slot: &Value, item_defs: &ItemRegistry) -> Option<Item> {
+    if slot.is_null() {
+        return None;
+    }
+    let key = slot["item"].as_str()?;
+    let mut item = match Item::from_key(item_defs, key, 1) {
+        Some(item) => item,
+        None => {
+            warn!("[WARN] unrecognised item {key} in save, dropping it");
+            return None;
+        }
+    };
+    // Edited saves can have anything in them, a stack still has to fit in a slot
+    let count = slot["count"].as_u64().unwrap_or(1);
+    item.count = count.clamp(1, item.def.max_stack as u64) as u8;
+    Some(item)
+}
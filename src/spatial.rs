@@ -0,0 +1,85 @@
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+use crate::logic::TILE;
+
+// Big enough that most things sit in one or two cells, small enough that a cell holds few things
+pub const CELL_SIZE: f32 = 4. * TILE;
+
+/// A uniform grid where each cell remembers the index of everything overlapping it, so finding
+/// what is near a rect doesn't mean looking at everything
+#[derive(Clone, Debug, Default)]
+pub struct SpatialGrid {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_rects(rects: impl IntoIterator<Item = Rect>) -> Self {
+        let mut grid = Self::new();
+        for (index, rect) in rects.into_iter().enumerate() {
+            grid.insert(index, rect);
+        }
+        grid
+    }
+
+    pub fn insert(&mut self, index: usize, rect: Rect) {
+        for cell in cells_of(rect) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear()
+    }
+
+    /// Indices of everything that might overlap rect, in increasing order. They share a cell
+    /// with it, which doesn't mean they actually overlap
+    pub fn query(&self, rect: Rect) -> Vec<usize> {
+        let mut found: Vec<usize> = cells_of(rect)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+fn cells_of(rect: Rect) -> impl Iterator<Item = (i32, i32)> {
+    let left = (rect.left() / CELL_SIZE).floor() as i32;
+    let right = (rect.right() / CELL_SIZE).floor() as i32;
+    let top = (rect.top() / CELL_SIZE).floor() as i32;
+    let bottom = (rect.bottom() / CELL_SIZE).floor() as i32;
+    (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+}
+
+/// A map's walls, which never move once it's loaded, so the grid over them is built just once
+#[derive(Clone, Debug, Default)]
+pub struct Walls {
+    rects: Vec<Rect>,
+    grid: SpatialGrid,
+}
+
+impl Walls {
+    pub fn new(rects: Vec<Rect>) -> Self {
+        let grid = SpatialGrid::from_rects(rects.iter().copied());
+        Self { rects, grid }
+    }
+
+    pub fn all(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// The walls that could be touching rect
+    pub fn near(&self, rect: Rect) -> impl Iterator<Item = &Rect> {
+        self.grid
+            .query(rect)
+            .into_iter()
+            .map(|index| &self.rects[index])
+    }
+}
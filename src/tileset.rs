@@ -0,0 +1,330 @@
+use macroquad::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Tiled keeps how a tile is flipped in the top bits of its id
+const FLIPPED_HORIZONTALLY: u32 = 0x80000000;
+const FLIPPED_VERTICALLY: u32 = 0x40000000;
+const FLIPPED_DIAGONALLY: u32 = 0x20000000;
+// Only used by hexagonal maps, but it still has to be cleared
+const ROTATED_HEXAGONAL: u32 = 0x10000000;
+
+/// A cell of a tile layer
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tile {
+    pub gid: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // Swaps x and y, which is done before the other two flips
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    pub fn from_raw(raw: u32) -> Self {
+        let flags = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY;
+        Self {
+            gid: raw & !(flags | ROTATED_HEXAGONAL),
+            flip_x: raw & FLIPPED_HORIZONTALLY != 0,
+            flip_y: raw & FLIPPED_VERTICALLY != 0,
+            flip_diagonal: raw & FLIPPED_DIAGONALLY != 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gid == 0
+    }
+
+    /// The same transform as (rotation, flip_x, flip_y) for DrawTextureParams, which flips the
+    /// texture first and then rotates it clockwise
+    pub fn transform(&self) -> (f32, bool, bool) {
+        use std::f32::consts::FRAC_PI_2;
+        match (self.flip_diagonal, self.flip_x, self.flip_y) {
+            (false, flip_x, flip_y) => (0., flip_x, flip_y),
+            (true, false, false) => (FRAC_PI_2, false, true),
+            (true, true, false) => (FRAC_PI_2, false, false),
+            (true, false, true) => (-FRAC_PI_2, false, false),
+            (true, true, true) => (FRAC_PI_2, true, false),
+        }
+    }
+}
+
+/// One of the tilesets a map uses. Global tile ids from firstgid onward belong to it
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub firstgid: u32,
+    // The name the image was loaded under in Textures, which is the file name without extension
+    pub texture: Rc<str>,
+    // Where the image is, for tilesets whose image is not in res/
+    pub image: PathBuf,
+    pub columns: u32,
+    pub tilecount: u32,
+    pub tile_size: Vec2,
+    pub margin: f32,
+    pub spacing: f32,
+    // Keyed by the id of the tile inside this tileset, not the global one
+    pub animations: HashMap<u32, Animation>,
+}
+
+/// The frames Tiled lets you give a tile, played on loop
+#[derive(Clone, Debug)]
+pub struct Animation {
+    // (local tile id, how long it stays up in milliseconds)
+    frames: Vec<(u32, u64)>,
+    total: u64,
+}
+
+impl Animation {
+    fn from_json(frames: &Value) -> Result<Self, String> {
+        let frames = frames.as_array().ok_or("\"animation\" is not a list")?;
+        let frames: Option<Vec<(u32, u64)>> = frames
+            .iter()
+            .map(|frame| {
+                Some((
+                    frame["tileid"].as_u64()? as u32,
+                    frame["duration"].as_u64()?,
+                ))
+            })
+            .collect();
+        let frames = frames.ok_or("animation frame without a tileid or duration")?;
+        let total = frames.iter().map(|(_, duration)| duration).sum();
+        if total == 0 {
+            return Err("animation takes no time at all".to_string());
+        }
+        Ok(Self { frames, total })
+    }
+
+    /// Which tile is showing after clock seconds
+    pub fn frame_at(&self, clock: f64) -> u32 {
+        let mut time = (clock * 1000.) as u64 % self.total;
+        for (tile, duration) in &self.frames {
+            if time < *duration {
+                return *tile;
+            }
+            time -= duration;
+        }
+        self.frames[0].0
+    }
+}
+
+impl Tileset {
+    /// Reads an entry of a map's "tilesets" array. map_dir is needed since both external tileset
+    /// files and images are relative to the file that mentions them
+    pub fn from_map(entry: &Value, map_dir: &Path) -> Result<Self, String> {
+        let firstgid = entry["firstgid"]
+            .as_u64()
+            .ok_or("field \"firstgid\" is missing")? as u32;
+
+        let (table, dir) = match entry["source"].as_str() {
+            Some(source) => {
+                let path = map_dir.join(source);
+                let dir = path.parent().unwrap_or(map_dir).to_path_buf();
+                (load_external(&path)?, dir)
+            }
+            None => (entry.clone(), map_dir.to_path_buf()),
+        };
+        Self::from_json(firstgid, &table, &dir)
+    }
+
+    fn from_json(firstgid: u32, table: &Value, dir: &Path) -> Result<Self, String> {
+        let field = |name: &str| -> Result<u64, String> {
+            table[name]
+                .as_u64()
+                .ok_or(format!("field \"{name}\" is missing"))
+        };
+        let image = table["image"]
+            .as_str()
+            .ok_or("only tilesets made from a single image are supported")?;
+        if !dir.join(image).exists() {
+            return Err(format!("image {image} does not exist"));
+        }
+        let texture = Path::new(image)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split_once('.'))
+            .ok_or(format!("can not tell the texture name of {image}"))?
+            .0;
+
+        let mut animations = HashMap::new();
+        for tile in table["tiles"].as_array().unwrap_or(&vec![]) {
+            if tile["animation"].is_null() {
+                continue;
+            }
+            let id = tile["id"].as_u64().ok_or("tile without an id")? as u32;
+            let animation = Animation::from_json(&tile["animation"])
+                .map_err(|err| format!("tile {id}: {err}"))?;
+            animations.insert(id, animation);
+        }
+
+        let columns = field("columns")? as u32;
+        if columns == 0 {
+            return Err("tileset has 0 columns".to_string());
+        }
+
+        Ok(Self {
+            firstgid,
+            texture: texture.into(),
+            image: dir.join(image),
+            columns,
+            tilecount: field("tilecount")? as u32,
+            tile_size: vec2(field("tilewidth")? as f32, field("tileheight")? as f32),
+            margin: table["margin"].as_u64().unwrap_or(0) as f32,
+            spacing: table["spacing"].as_u64().unwrap_or(0) as f32,
+            animations,
+        })
+    }
+
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.firstgid && gid < self.firstgid + self.tilecount
+    }
+
+    /// The tile to draw in place of gid after clock seconds, which is just gid if it's not animated
+    pub fn animated_gid(&self, gid: u32, clock: f64) -> u32 {
+        match self.animations.get(&(gid - self.firstgid)) {
+            Some(animation) => self.firstgid + animation.frame_at(clock),
+            None => gid,
+        }
+    }
+
+    /// Where a global tile id is on this tileset's texture
+    pub fn source_rect(&self, gid: u32) -> Rect {
+        let local = gid - self.firstgid;
+        let col = (local % self.columns) as f32;
+        let row = (local / self.columns) as f32;
+        Rect::new(
+            self.margin + col * (self.tile_size.x + self.spacing),
+            self.margin + row * (self.tile_size.y + self.spacing),
+            self.tile_size.x,
+            self.tile_size.y,
+        )
+    }
+}
+
+/// Finds which tileset a global tile id belongs to. 0 means no tile at all
+pub fn find_tileset(tilesets: &[Tileset], gid: u32) -> Option<&Tileset> {
+    if gid == 0 {
+        return None;
+    }
+    tilesets.iter().find(|tileset| tileset.contains(gid))
+}
+
+fn load_external(path: &Path) -> Result<Value, String> {
+    let content = read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let is_xml = path.extension().map(|ext| ext == "tsx").unwrap_or(false);
+    match is_xml {
+        true => parse_tsx(&content),
+        // Tiled can also save tilesets as JSON (.tsj or .json)
+        false => serde_json::from_str(&content).map_err(|err| format!("{}: {err}", path.display())),
+    }
+}
+
+/// Turns a .tsx file into the same JSON Tiled would have embedded into the map. This only knows
+/// enough XML to read what Tiled writes, which is a flat list of tags with attributes
+pub fn parse_tsx(content: &str) -> Result<Value, String> {
+    let mut tileset: Option<Map<String, Value>> = None;
+    let mut tiles: Vec<Value> = vec![];
+
+    for tag in xml_tags(content) {
+        let (name, attributes) = tag?;
+        let attribute = |key: &str| -> Value {
+            let value = attributes.iter().find(|(name, _)| *name == key);
+            value.map(|(_, value)| xml_value(value)).unwrap_or_default()
+        };
+        match name {
+            "tileset" => {
+                let mut table = Map::new();
+                for (key, value) in attributes {
+                    table.insert(key.to_string(), xml_value(&value));
+                }
+                tileset = Some(table);
+            }
+            "tile" => tiles.push(json!({ "id": attribute("id"), "animation": [] })),
+            "frame" => {
+                let tile = tiles.last_mut().ok_or("<frame> outside of <tile>")?;
+                let frame =
+                    json!({ "tileid": attribute("tileid"), "duration": attribute("duration") });
+                tile["animation"].as_array_mut().unwrap().push(frame);
+            }
+            // Tiles can have their own images, but those tilesets are not supported anyway
+            "image" if tiles.is_empty() => {
+                let table = tileset.as_mut().ok_or("<image> outside of <tileset>")?;
+                for (key, value) in attributes {
+                    let key = match key {
+                        "source" => "image".to_string(),
+                        other => format!("image{other}"),
+                    };
+                    table.insert(key, xml_value(&value));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut table = tileset.ok_or("no <tileset> tag")?;
+    tiles.retain(|tile| {
+        tile["animation"]
+            .as_array()
+            .is_some_and(|frames| !frames.is_empty())
+    });
+    table.insert("tiles".to_string(), Value::Array(tiles));
+    table.entry("margin").or_insert(json!(0));
+    table.entry("spacing").or_insert(json!(0));
+    Ok(Value::Object(table))
+}
+
+// Numbers stay numbers, like they would in a JSON tileset
+fn xml_value(value: &str) -> Value {
+    match value.parse::<u64>() {
+        Ok(number) => json!(number),
+        Err(_) => json!(value),
+    }
+}
+
+type XmlTag<'a> = Result<(&'a str, Vec<(&'a str, String)>), String>;
+
+// Yields every opening tag along with its attributes, closing tags and comments are skipped
+fn xml_tags(content: &str) -> impl Iterator<Item = XmlTag<'_>> {
+    content.split('<').skip(1).filter_map(|chunk| {
+        let tag = chunk.split('>').next()?;
+        if tag.starts_with('/') || tag.starts_with('?') || tag.starts_with('!') {
+            return None;
+        }
+        let tag = tag.trim_end_matches('/').trim();
+        let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+        let mut attributes = vec![];
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let (key, value) = match rest.split_once('=') {
+                Some(pair) => pair,
+                None => return Some(Err(format!("bad attribute in <{name}>"))),
+            };
+            let value = value.trim_start();
+            let quote = match value.chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Some(Err(format!("unquoted attribute {key} in <{name}>"))),
+            };
+            let (value, remaining) = match value[1..].split_once(quote) {
+                Some(pair) => pair,
+                None => return Some(Err(format!("unclosed attribute {key} in <{name}>"))),
+            };
+            attributes.push((key.trim(), unescape(value)));
+            rest = remaining;
+        }
+        Some(Ok((name, attributes)))
+    })
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,48 @@
+use crate::input::InputFrame;
+use crate::logic::Game;
+
+pub const FIXED_DT: f32 = 1. / 60.;
+// A frame hitch longer than this gets dropped instead of being simulated all at once
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Runs Game::tick at a fixed rate no matter how fast frames are being drawn. Whatever is left in
+/// the accumulator after stepping is how far the renderer should interpolate into the next step.
+pub struct FixedTimestep {
+    accumulator: f32,
+    pending: InputFrame,
+}
+
+impl FixedTimestep {
+    pub fn new() -> Self {
+        Self {
+            accumulator: 0.,
+            pending: InputFrame::new(FIXED_DT),
+        }
+    }
+
+    /// Queues up this frame's input and runs as many ticks as the elapsed time allows
+    pub fn advance(&mut self, game: &mut Game, frame: InputFrame) {
+        self.accumulator += frame.dt.min(MAX_FRAME_TIME);
+        self.pending.merge(frame);
+
+        while self.accumulator >= FIXED_DT {
+            let mut input = self.pending.clone();
+            input.dt = FIXED_DT;
+            game.tick(input);
+            // A key press should only be seen by one tick
+            self.pending.clear_events();
+            self.accumulator -= FIXED_DT;
+        }
+    }
+
+    /// How far between the last tick and the next one we are, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / FIXED_DT
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,16 +3,14 @@ use std::collections::HashMap;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct DeathScreen {
     pub buttons: HashMap<String, Button>,
 }
 
 impl DeathScreen {
     pub fn new() -> Self {
-        Self {
-            buttons: HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn update(&mut self, screen_box: Rect) {
@@ -27,18 +25,18 @@ impl DeathScreen {
         self.buttons.insert("Menu".to_string(), main_menu_button);
     }
 
-    pub fn draw_buttons(&self, texture: &Texture2D, font: &Font) {
+    pub fn draw_buttons(&self, texture: &Texture2D, font: Option<&Font>) {
         let mesh = button_mesh();
 
         for (name, button) in self.buttons.iter() {
             draw_tiles(&mesh, button.hitbox.point(), texture, None, TILE_SIZE);
             let params = TextParams {
-                font: Some(font),
+                font,
                 font_size: 80,
                 color: BLACK,
                 ..Default::default()
             };
-            let rect = measure_text(name, Some(font), params.font_size, 1.);
+            let rect = measure_text(name, font, params.font_size, 1.);
             let delta_x = (button.hitbox.w - rect.width) / 2.;
             let delta_y = (button.hitbox.h - rect.height) / 2.;
             let text_box = vec2(button.hitbox.x + delta_x, button.hitbox.y + delta_y);
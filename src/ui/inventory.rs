@@ -5,11 +5,13 @@ use crate::weapons::Weapon;
 use macroquad::prelude::*;
 use std::mem::swap;
 
-use super::items::{Item, ItemID, ItemType};
+use super::items::{Item, ItemType};
 
 const ROW: u8 = 3;
 const COL: f32 = 4.;
 const SIZE: f32 = 140.;
+// The first row of the inventory doubles as the hotbar, used with the number keys
+pub const HOTBAR: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
 
 #[derive(Clone)]
 pub struct Inventory {
@@ -29,29 +31,78 @@ impl Inventory {
         }
     }
 
-    pub fn first_of_type(&self, item: &Item) -> Option<usize> {
-        let mut index = 0;
-        for slot in &self.content {
-            if let Some(content) = slot {
-                if content.name() == item.name() {
-                    return Some(index);
-                }
-            } else {
-                return Some(index);
+    /// The item stuck to the cursor while the inventory is open
+    pub fn holding(&self) -> Option<&Item> {
+        self.holding.as_ref()
+    }
+
+    /// Puts an item on the cursor, used when loading a save
+    pub fn set_holding(&mut self, item: Option<Item>) {
+        self.holding = item
+    }
+
+    /// How many of the item there are across every slot
+    pub fn count(&self, id: &str) -> u32 {
+        self.content
+            .iter()
+            .chain([&self.holding])
+            .flatten()
+            .filter(|item| item.id() == id)
+            .map(|item| item.count as u32)
+            .sum()
+    }
+
+    /// Takes away that many of the item, from the held item first. Returns how many were missing
+    pub fn remove(&mut self, id: &str, mut count: u32) -> u32 {
+        for slot in [&mut self.holding]
+            .into_iter()
+            .chain(self.content.iter_mut())
+        {
+            let item = match slot {
+                Some(item) if item.id() == id => item,
+                _ => continue,
+            };
+            let taken = count.min(item.count as u32);
+            item.count -= taken as u8;
+            count -= taken;
+            if item.count == 0 {
+                *slot = None;
+            }
+            if count == 0 {
+                break;
             }
-            index += 1;
         }
-        return None;
+        count
     }
 
-    pub fn append(&mut self, item: Item) {
-        if let Some(slot) = self.first_of_type(&item) {
-            if self.content[slot].is_none() {
-                self.content[slot] = Some(item);
-                return;
+    /// Tops up stacks of the same item first, then fills empty slots. Gives back whatever didn't
+    /// fit
+    pub fn append(&mut self, mut item: Item) -> Option<Item> {
+        let max = item.def.max_stack;
+        // The last slot is for the weapon
+        for slot in self.content[..12].iter_mut().flatten() {
+            if !slot.is_same_type(&item) || slot.count >= max {
+                continue;
+            }
+            let moved = item.count.min(max - slot.count);
+            slot.count += moved;
+            item.count -= moved;
+            if item.count == 0 {
+                return None;
+            }
+        }
+        for slot in self.content[..12].iter_mut() {
+            if slot.is_some() {
+                continue;
+            }
+            let moved = item.count.min(max);
+            *slot = Some(Item::new(item.def.clone(), moved));
+            item.count -= moved;
+            if item.count == 0 {
+                return None;
             }
-            self.content[slot].as_mut().unwrap().count += 1
         }
+        Some(item)
     }
 }
 
@@ -64,6 +115,7 @@ impl Game {
         let (l_box, r_box) = dual_box(screen_center, width, height, margin);
         self.player.update_inv(r_box, l_box);
         self.inv_click_detection();
+        self.inv_use_detection();
     }
 
     pub fn show_inv(&self) {
@@ -82,6 +134,7 @@ impl Game {
         self.draw_held_item()
     }
 
+    // Needs a window, game logic should read InputFrame::mouse_pos instead
     pub fn get_mouse_pos(&self) -> Vec2 {
         let screen_width = screen_width();
         let screen_height = screen_height();
@@ -98,7 +151,7 @@ impl Game {
 
     // I'm .... not exactly proud of this one
     fn draw_description(&self) {
-        let mouse_pos = self.get_mouse_pos();
+        let mouse_pos = self.input.mouse_pos;
         let player_inv = &self.player.inventory;
         let mut index = 0;
         let mut diag_rect = Rect::new(
@@ -127,7 +180,7 @@ impl Game {
             let name = item.name();
             let mut param = TextParams {
                 color: BLACK,
-                font: Some(&self.font),
+                font: self.font.as_ref(),
                 font_size: 44,
                 ..Default::default()
             };
@@ -141,11 +194,23 @@ impl Game {
         }
     }
 
+    // Right clicking a consumable uses it
+    fn inv_use_detection(&mut self) {
+        if !self.input.mouse_pressed(MouseButton::Right) {
+            return;
+        }
+        let mouse_pos = self.input.mouse_pos;
+        let slots = self.player.inventory.slot_hitboxes;
+        if let Some(index) = slots[..12].iter().position(|slot| slot.contains(mouse_pos)) {
+            self.player.use_slot(index);
+        }
+    }
+
     fn inv_click_detection(&mut self) {
-        if !is_mouse_button_pressed(MouseButton::Left) {
+        if !self.input.mouse_pressed(MouseButton::Left) {
             return;
         }
-        let mouse_pos = self.get_mouse_pos();
+        let mouse_pos = self.input.mouse_pos;
         let player_inv = &mut self.player.inventory;
 
         let mut index = 0;
@@ -160,8 +225,14 @@ impl Game {
                 let slot = player_inv.content[index].as_mut();
                 if let Some(slot) = slot {
                     if slot.is_same_type(holding) {
-                        slot.count += holding.count;
-                        player_inv.holding = None;
+                        let moved = holding
+                            .count
+                            .min(slot.def.max_stack.saturating_sub(slot.count));
+                        slot.count += moved;
+                        holding.count -= moved;
+                        if holding.count == 0 {
+                            player_inv.holding = None;
+                        }
                         return;
                     }
                     swap(holding, slot);
@@ -183,7 +254,7 @@ impl Game {
             return;
         }
         if let Some(item) = player_inv.holding.clone() {
-            if item.class != ItemType::Weapon {
+            if item.def.class != ItemType::Weapon {
                 return;
             }
             if player_inv.content[12].is_some() {
@@ -193,7 +264,7 @@ impl Game {
 
             player_inv.content[12] = Some(item.clone());
             player_inv.holding = None;
-            self.player.held_weapon = get_weapon(&item.kind);
+            self.player.held_weapon = get_weapon(&item);
             return;
         }
         player_inv.holding = player_inv.content[12].clone();
@@ -202,9 +273,9 @@ impl Game {
 
     fn draw_held_item(&self) {
         if let Some(item) = &self.player.inventory.holding {
-            let mouse_pos = self.get_mouse_pos();
+            let mouse_pos = self.input.mouse_pos;
             let source = source_rect(Some(item));
-            if source == None {
+            if source.is_none() {
                 error!("Like how even?")
             }
             let dest_size = vec2(SIZE, SIZE) * 0.8;
@@ -262,7 +333,7 @@ impl Game {
             }
 
             let params = TextParams {
-                font: Some(&self.font),
+                font: self.font.as_ref(),
                 font_size: 24,
                 ..Default::default()
             };
@@ -310,7 +381,7 @@ impl Player {
     }
 }
 
-fn dual_box(screen_center: Vec2, width: f32, height: f32, margin: f32) -> (Rect, Rect) {
+pub fn dual_box(screen_center: Vec2, width: f32, height: f32, margin: f32) -> (Rect, Rect) {
     let ui_box = Rect::new(
         screen_center.x - margin / 2. - width,
         screen_center.y - height / 2.,
@@ -336,16 +407,11 @@ fn param() -> DrawTextureParams {
 }
 
 pub fn source_rect(item: Option<&Item>) -> Option<Rect> {
-    return match item?.kind {
-        ItemID::Slime => Some(Rect::new(0., TILE_SIZE * 2., TILE_SIZE, TILE_SIZE)),
-        ItemID::Mushroom => Some(Rect::new(TILE_SIZE, TILE_SIZE * 2., TILE_SIZE, TILE_SIZE)),
-        ItemID::RustySword => Some(Rect::new(0., 3. * TILE_SIZE, TILE_SIZE, TILE_SIZE)),
-        ItemID::BlackSword => Some(Rect::new(TILE_SIZE, 3. * TILE_SIZE, TILE_SIZE, TILE_SIZE)),
-    };
+    Some(item?.def.icon)
 }
 
 #[rustfmt::skip]
-fn window_texture() -> Vec<Vec<u16>> {
+pub fn window_texture() -> Vec<Vec<u16>> {
     vec![
         vec![7 ,  8,  8,  8,  9],
         vec![19, 20, 20, 20, 21],
@@ -367,13 +433,13 @@ fn desc_diag() -> Vec<Vec<u16>> {
     ]
 }
 
-fn get_weapon(kind: &ItemID) -> Weapon {
-    match kind {
-        ItemID::RustySword => Weapon::rusty_sword(),
-        ItemID::BlackSword => Weapon::black_sword(),
-        x => {
-            error!("{x:?} isn't handled, returning rusty_sword anyway");
-            Weapon::rusty_sword()
+pub fn get_weapon(item: &Item) -> Weapon {
+    match &item.def.weapon {
+        Some(weapon) => weapon.clone(),
+        None => {
+            let id = item.id();
+            error!("{id} isn't a weapon, using the default one anyway");
+            Weapon::default()
         }
     }
 }
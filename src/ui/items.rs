@@ -1,16 +1,42 @@
 use macroquad::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::rc::Rc;
 
 use super::inventory::source_rect;
+use crate::effects::Effect;
+use crate::map::{MapErrorKind, MapLoadError};
+use crate::weapons::Weapon;
 
-#[allow(dead_code)]
+/// An item in the inventory or on the ground. What it is lives in the registry, see ItemDef
 #[derive(Clone, Debug)]
 pub struct Item {
-    value: u32,
-    pub kind: ItemID,
-    pub class: ItemType,
+    pub def: Rc<ItemDef>,
     pub count: u8,
 }
 
+/// One entry of assets/items.json
+#[derive(Debug)]
+pub struct ItemDef {
+    // The key in items.json, which is also what map files and saves call it
+    pub id: Rc<str>,
+    pub name: String,
+    pub description: String,
+    // Where it is on the ui texture
+    pub icon: Rect,
+    pub value: u32,
+    pub class: ItemType,
+    pub max_stack: u8,
+    // Only for weapons
+    pub weapon: Option<Weapon>,
+    // Only for consumables, all of them happen at once when it's used
+    pub effects: Vec<Effect>,
+}
+
+/// Every item in assets/items.json, by id
+pub type ItemRegistry = HashMap<Rc<str>, Rc<ItemDef>>;
+
 #[derive(Debug, Clone)]
 pub struct ItemEntity {
     pub item: Item,
@@ -22,73 +48,90 @@ pub struct ItemEntity {
 pub enum ItemType {
     RegularItem,
     Weapon,
+    Consumable,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ItemID {
-    Slime,
-    Mushroom,
-    RustySword,
-    BlackSword,
-}
+impl ItemDef {
+    pub fn from_json(id: &str, json: &Value) -> Result<Self, MapErrorKind> {
+        let text = |field: &'static str| {
+            json[field]
+                .as_str()
+                .map(|text| text.to_string())
+                .ok_or(MapErrorKind::Field(field))
+        };
+        let icon: Option<Vec<f32>> = json["icon"].as_array().map(|nums| {
+            nums.iter()
+                .filter_map(|num| num.as_f64())
+                .map(|num| num as f32)
+                .collect()
+        });
+        let icon = match icon.as_deref() {
+            Some([x, y, w, h]) => Rect::new(*x, *y, *w, *h),
+            _ => return Err(MapErrorKind::Field("icon")),
+        };
+        let class = match json["class"].as_str() {
+            Some("item") => ItemType::RegularItem,
+            Some("weapon") => ItemType::Weapon,
+            Some("consumable") => ItemType::Consumable,
+            _ => return Err(MapErrorKind::Field("class")),
+        };
+        let weapon = match class {
+            ItemType::Weapon => {
+                Some(Weapon::from_json(&json["weapon"]).ok_or(MapErrorKind::Field("weapon"))?)
+            }
+            _ => None,
+        };
+        let effects = match (&class, &json["effects"]) {
+            (ItemType::Consumable, Value::Array(effects)) => effects
+                .iter()
+                .map(Effect::from_json)
+                .collect::<Result<_, _>>()?,
+            (ItemType::Consumable, _) => return Err(MapErrorKind::Field("effects")),
+            _ => vec![],
+        };
+        let max_stack = match json["max_stack"].as_u64().unwrap_or(1) {
+            0 => return Err(MapErrorKind::Field("max_stack")),
+            max => max.min(u8::MAX as u64) as u8,
+        };
 
-impl Item {
-    pub fn slime(count: u8) -> Self {
-        Item {
-            kind: ItemID::Slime,
-            value: 5,
-            count,
-            class: ItemType::RegularItem,
-        }
+        Ok(Self {
+            id: id.into(),
+            name: text("name")?,
+            description: text("description")?,
+            icon,
+            value: json["value"].as_u64().unwrap_or(0) as u32,
+            class,
+            max_stack,
+            weapon,
+            effects,
+        })
     }
+}
 
-    pub fn mushroom(count: u8) -> Self {
-        Item {
-            kind: ItemID::Mushroom,
-            value: 5,
-            count,
-            class: ItemType::RegularItem,
-        }
+impl Item {
+    pub fn new(def: Rc<ItemDef>, count: u8) -> Self {
+        Self { def, count }
     }
 
-    pub fn rusty_sword() -> Self {
-        Self {
-            kind: ItemID::RustySword,
-            count: 1,
-            class: ItemType::Weapon,
-            value: 10,
-        }
+    /// None if there's no such item in the registry
+    pub fn from_key(items: &ItemRegistry, key: &str, count: u8) -> Option<Self> {
+        Some(Self::new(items.get(key)?.clone(), count))
     }
 
-    pub fn black_sword() -> Self {
-        Self {
-            kind: ItemID::BlackSword,
-            count: 1,
-            class: ItemType::Weapon,
-            value: 20,
-        }
+    pub fn id(&self) -> &str {
+        &self.def.id
     }
 
     pub fn name(&self) -> &str {
-        match self.kind {
-            ItemID::Mushroom => "Nấm Đỏ",
-            ItemID::Slime => "Chất nhầy",
-            ItemID::RustySword => "Kiếm rỉ sét",
-            ItemID::BlackSword => "Hắc kiếm",
-        }
+        &self.def.name
     }
 
     pub fn description(&self) -> &str {
-        match self.kind {
-            ItemID::Mushroom => "Trái với một tựa game nổi tiếng nào đó, việc tiêu thụ loại nấm này sẽ không làm bạn cao lên",
-            ItemID::Slime => "Nó khá nhầy nhụa",
-            ItemID::BlackSword => "Một thanh kiếm với màu đen huyền bí",
-            ItemID::RustySword => "Một thanh kiếm đã bị rỉ sét"
-        }
+        &self.def.description
     }
 
     pub fn is_same_type(&self, item: &Item) -> bool {
-        self.kind == item.kind
+        self.def.id == item.def.id
     }
 }
 impl ItemEntity {
@@ -113,3 +156,31 @@ impl ItemEntity {
         draw_texture_ex(texture, self.hitbox.x, self.hitbox.y, WHITE, params)
     }
 }
+
+/// Reads assets/items.json, reporting every broken item at once
+pub fn load_items() -> Result<ItemRegistry, Vec<MapLoadError>> {
+    let file = "assets/items.json";
+    let in_file = |kind| vec![MapLoadError::new(kind).in_file(file)];
+    let json = read_to_string(file).map_err(|err| in_file(MapErrorKind::Io(err.to_string())))?;
+    let json: Value =
+        serde_json::from_str(&json).map_err(|err| in_file(MapErrorKind::Json(err.to_string())))?;
+    let table = json
+        .as_object()
+        .ok_or_else(|| in_file(MapErrorKind::Field("items")))?;
+
+    let mut items = ItemRegistry::new();
+    let mut errors = vec![];
+    for (id, item) in table {
+        match ItemDef::from_json(id, item) {
+            Ok(def) => {
+                items.insert(def.id.clone(), Rc::new(def));
+            }
+            // Items have no layer, this says which one it was
+            Err(kind) => errors.push(MapLoadError::new(kind).in_file(file).in_layer(id)),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(items),
+        false => Err(errors),
+    }
+}
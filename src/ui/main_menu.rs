@@ -5,22 +5,46 @@ use macroquad::prelude::*;
 use super::{Button, PIXEL};
 use crate::camera::draw_tiles;
 use crate::logic::*;
+use crate::save::{slot_summary, SAVE_SLOTS};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct MainMenu {
     pub buttons: HashMap<String, Button>,
+    pub page: MenuPage,
+    // What is in each save slot, only read from disk when one of the slot pages is opened
+    pub slots: Vec<Option<String>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum MenuPage {
+    #[default]
+    Main,
+    Save,
+    Load,
 }
 
 impl MainMenu {
     pub fn update(&mut self, screen_box: Rect) {
+        self.buttons.clear();
         let size = vec2(5., 2.) * TILE;
         let mut pos = screen_box.center();
-        pos.y -= TILE;
-        let play_button = Button::size(size).center_on(pos);
-        pos.y += 2. * TILE;
-        let quit_button = Button::size(size).center_on(pos);
-        self.buttons.insert("Chơi".to_string(), play_button);
-        self.buttons.insert("Thoát".to_string(), quit_button);
+        pos.y -= 3. * TILE;
+        let labels = match self.page {
+            MenuPage::Main => vec!["Chơi", "Lưu", "Tải", "Thoát"],
+            MenuPage::Save | MenuPage::Load => vec!["Ô 1", "Ô 2", "Ô 3", "Trở lại"],
+        };
+        for label in labels {
+            let button = Button::size(size).center_on(pos);
+            self.buttons.insert(label.to_string(), button);
+            pos.y += 2. * TILE;
+        }
+    }
+
+    pub fn open_page(&mut self, page: MenuPage) {
+        if page != MenuPage::Main {
+            self.slots = (0..SAVE_SLOTS).map(slot_summary).collect();
+        }
+        self.page = page;
     }
 
     pub fn draw_background(&self, bg: &Texture2D, screen_box: Rect) {
@@ -33,17 +57,17 @@ impl MainMenu {
         draw_texture_ex(bg, screen_box.x, screen_box.y, WHITE, params)
     }
 
-    pub fn draw_buttons(&self, texture: &Texture2D, font: &Font) {
+    pub fn draw_buttons(&self, texture: &Texture2D, font: Option<&Font>) {
         let mesh = button_mesh();
         for (name, button) in self.buttons.iter() {
             draw_tiles(&mesh, button.hitbox.point(), texture, None, TILE_SIZE);
             let params = TextParams {
-                font: Some(font),
+                font,
                 font_size: 60,
                 color: BLACK,
                 ..Default::default()
             };
-            let rect = measure_text(name, Some(font), params.font_size, 1.);
+            let rect = measure_text(name, font, params.font_size, 1.);
             let delta_x = (button.hitbox.w - rect.width) / 2.;
             let delta_y = (button.hitbox.h - rect.height) / 2.;
             let text_box = vec2(button.hitbox.x + delta_x, button.hitbox.y + delta_y);
@@ -52,16 +76,46 @@ impl MainMenu {
                 text_box.x,
                 button.hitbox.center().y + 3. * PIXEL,
                 params,
-            )
+            );
+            self.draw_slot_summary(name, button, font);
         }
     }
+
+    // Shows which map a save was made on to the right of its button
+    fn draw_slot_summary(&self, name: &str, button: &Button, font: Option<&Font>) {
+        let slot = match slot_index(name) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let summary = match self.slots.get(slot) {
+            Some(Some(map)) => map.as_str(),
+            _ => "Trống",
+        };
+        let params = TextParams {
+            font,
+            font_size: 40,
+            color: WHITE,
+            ..Default::default()
+        };
+        draw_text_ex(
+            summary,
+            button.hitbox.right() + 4. * PIXEL,
+            button.hitbox.center().y + 2. * PIXEL,
+            params,
+        )
+    }
+
     pub fn new() -> Self {
-        Self {
-            buttons: HashMap::new(),
-        }
+        Self::default()
     }
 }
 
+/// Turns a slot button's name back into the slot it stands for
+pub fn slot_index(name: &str) -> Option<usize> {
+    let number: usize = name.strip_prefix("Ô ")?.parse().ok()?;
+    number.checked_sub(1)
+}
+
 pub fn button_mesh() -> Vec<Vec<u16>> {
     vec![vec![10, 11, 11, 11, 12], vec![22, 23, 23, 23, 24]]
 }
@@ -1,5 +1,6 @@
 use macroquad::prelude::*;
 
+use crate::input::InputFrame;
 use crate::logic::*;
 use crate::player::*;
 use crate::{GUIType, GameState};
@@ -8,9 +9,12 @@ pub mod death_screen;
 pub mod inventory;
 pub mod items;
 pub mod main_menu;
+pub mod quest_log;
+pub mod shop;
 
 pub use death_screen::*;
 pub use main_menu::*;
+pub use shop::Shop;
 
 #[derive(Clone, Debug)]
 pub struct Button {
@@ -18,11 +22,13 @@ pub struct Button {
 }
 
 impl Button {
-    fn is_clicked(&self, mouse_pos: Vec2) -> bool {
-        if !is_mouse_button_down(MouseButton::Left) {
+    fn is_clicked(&self, input: &InputFrame) -> bool {
+        // Pressed rather than down, otherwise the click would carry over to whatever button ends
+        // up under the cursor on the next page
+        if !input.mouse_pressed(MouseButton::Left) {
             return false;
         }
-        if !self.hitbox.contains(mouse_pos) {
+        if !self.hitbox.contains(input.mouse_pos) {
             return false;
         }
         true
@@ -44,6 +50,106 @@ impl Button {
 impl Game {
     pub fn hud(&self) {
         self.draw_health_bar();
+        self.draw_hotbar();
+        self.draw_coins();
+        self.draw_boss_health_bar();
+    }
+
+    // Under the hotbar
+    fn draw_coins(&self) {
+        let screen = self.cam_box();
+        let params = TextParams {
+            font: self.font.as_ref(),
+            font_size: 36,
+            color: GOLD,
+            ..Default::default()
+        };
+        let coins = format!("{} xu", self.player.coins);
+        let y = screen.y + TILE * 1.6 + 7. * PIXEL;
+        draw_text_ex(&coins, screen.x + 3. * PIXEL, y, params);
+    }
+
+    // The first row of the inventory, under the health bar
+    fn draw_hotbar(&self) {
+        let screen = self.cam_box();
+        let texture = &self.textures["ui"];
+        let size = TILE * 0.6;
+        for (index, slot) in self.player.inventory.content[..4].iter().enumerate() {
+            let x = screen.x + 3. * PIXEL + index as f32 * (size + PIXEL);
+            let y = screen.y + TILE + PIXEL;
+            let params = DrawTextureParams {
+                source: Some(Rect::new(0., 0., TILE_SIZE, TILE_SIZE)),
+                dest_size: Some(vec2(size, size)),
+                ..Default::default()
+            };
+            draw_texture_ex(texture, x, y, WHITE, params);
+
+            let params = TextParams {
+                font: self.font.as_ref(),
+                font_size: 20,
+                color: WHITE,
+                ..Default::default()
+            };
+            draw_text_ex(
+                &format!("{}", index + 1),
+                x + PIXEL,
+                y + 4. * PIXEL,
+                params.clone(),
+            );
+            let item = match slot {
+                Some(item) => item,
+                None => continue,
+            };
+            let params_icon = DrawTextureParams {
+                source: inventory::source_rect(Some(item)),
+                dest_size: Some(vec2(size, size) * 0.8),
+                ..Default::default()
+            };
+            let padding = size * 0.1;
+            draw_texture_ex(texture, x + padding, y + padding, WHITE, params_icon);
+            if item.count > 1 {
+                draw_text_ex(
+                    &format!("{}", item.count),
+                    x + size - 5. * PIXEL,
+                    y + size - PIXEL,
+                    params,
+                );
+            }
+        }
+    }
+
+    // Along the bottom of the screen, with the boss's name above it
+    fn draw_boss_health_bar(&self) {
+        let boss = match self.maps[&self.current_map].boss() {
+            Some(boss) => boss,
+            None => return,
+        };
+        let screen = self.cam_box();
+        let health = boss.get_props().health.max(0.) / boss.max_health();
+        let bar = Rect::new(
+            screen.x + screen.w * 0.2,
+            screen.bottom() - 10. * PIXEL,
+            screen.w * 0.6,
+            4. * PIXEL,
+        );
+        let border = PIXEL;
+        draw_rectangle(
+            bar.x - border,
+            bar.y - border,
+            bar.w + 2. * border,
+            bar.h + 2. * border,
+            BLACK,
+        );
+        draw_rectangle(bar.x, bar.y, bar.w * health, bar.h, RED);
+
+        let title = boss.boss_title().unwrap_or_default();
+        let params = TextParams {
+            font: self.font.as_ref(),
+            font_size: 48,
+            color: WHITE,
+            ..Default::default()
+        };
+        draw_text_ex(title, bar.x, bar.y - 2. * PIXEL, params);
     }
 
     fn draw_health_bar(&self) {
@@ -71,33 +177,64 @@ impl Game {
 
     fn tick_main_menu(&mut self, mut menu: MainMenu) -> GameState {
         menu.update(self.cam_box());
-        let mouse_pos = self.get_mouse_pos();
-        if menu.buttons["Chơi"].is_clicked(mouse_pos) {
-            return GameState::Normal;
+        let clicked = menu
+            .buttons
+            .iter()
+            .find(|(_, button)| button.is_clicked(&self.input))
+            .map(|(name, _)| name.clone());
+
+        if menu.page != MenuPage::Main && self.input.key_pressed(KeyCode::Escape) {
+            menu.open_page(MenuPage::Main);
         }
-        if menu.buttons["Thoát"].is_clicked(mouse_pos) {
-            return GameState::Quit;
+        let clicked = match clicked {
+            Some(clicked) => clicked,
+            None => return GameState::GUI(GUIType::MainMenu(menu)),
+        };
+
+        match clicked.as_str() {
+            "Chơi" => return GameState::Normal,
+            "Thoát" => return GameState::Quit,
+            "Lưu" => menu.open_page(MenuPage::Save),
+            "Tải" => menu.open_page(MenuPage::Load),
+            "Trở lại" => menu.open_page(MenuPage::Main),
+            name => {
+                let slot = match slot_index(name) {
+                    Some(slot) => slot,
+                    None => return GameState::GUI(GUIType::MainMenu(menu)),
+                };
+                if menu.page == MenuPage::Save {
+                    if let Err(err) = self.save_to_slot(slot) {
+                        error!("Could not save to slot {}: {err}", slot + 1);
+                    }
+                    menu.open_page(MenuPage::Save);
+                } else {
+                    match self.load_from_slot(slot) {
+                        Ok(()) => return GameState::Normal,
+                        Err(err) => error!("Could not load slot {}: {err}", slot + 1),
+                    }
+                }
+            }
         }
         GameState::GUI(GUIType::MainMenu(menu))
     }
 
     fn tick_death_screen(&mut self, mut menu: DeathScreen) -> GameState {
         menu.update(self.cam_box());
-        let mouse_pos = self.get_mouse_pos();
 
-        if menu.buttons["Hồi Sinh"].is_clicked(mouse_pos) {
+        if menu.buttons["Hồi Sinh"].is_clicked(&self.input) {
             let pos = self.player.spawn_loc.location;
             let map = self.player.spawn_loc.map.clone();
 
             let state = GameState::Transition(Transition::new(pos, map));
             self.player.state = PlayerState::Transition;
             self.player.props.health = PLAYER_HEALTH;
+            self.player.buffs.clear();
             return state;
         }
-        if menu.buttons["Menu"].is_clicked(mouse_pos) {
+        if menu.buttons["Menu"].is_clicked(&self.input) {
             return GameState::GUI(GUIType::MainMenu(MainMenu::new()));
         }
-        return GameState::GUI(GUIType::DeathScreen(menu));
+        GameState::GUI(GUIType::DeathScreen(menu))
     }
 
     pub fn tick_gui(&mut self) {
@@ -110,6 +247,13 @@ impl Game {
                 self.tick_inv();
                 return;
             }
+            // Only ever closed, by key_event_handler
+            GUIType::QuestLog => return,
+            GUIType::Shop(shop) => {
+                let shop = shop.clone();
+                self.tick_shop(&shop);
+                return;
+            }
             GUIType::MainMenu(main_menu) => self.tick_main_menu(main_menu.clone()),
             GUIType::DeathScreen(death_screen) => self.tick_death_screen(death_screen.clone()),
         }
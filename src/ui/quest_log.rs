@@ -0,0 +1,99 @@
+use macroquad::prelude::*;
+
+use crate::camera::draw_tiles;
+use crate::logic::{Game, TILE, TILE_SIZE};
+use crate::player::PIXEL;
+
+impl Game {
+    /// Every quest that's been taken, the ones still going first
+    pub fn show_quest_log(&self) {
+        let window = Rect::new(0., 0., 9. * TILE, 7. * TILE);
+        let center = self.cam_box().center();
+        let origin = center - window.size() / 2.;
+        draw_tiles(
+            &window_texture(),
+            origin,
+            &self.textures["ui"],
+            None,
+            TILE_SIZE,
+        );
+
+        let title = TextParams {
+            font: self.font.as_ref(),
+            font_size: 50,
+            color: BLACK,
+            ..Default::default()
+        };
+        let line = TextParams {
+            font_size: 40,
+            ..title.clone()
+        };
+        let x = origin.x + 8. * PIXEL;
+        let mut y = origin.y + 15. * PIXEL;
+        draw_text_ex("Nhiệm vụ", x, y, title.clone());
+        y += 6. * PIXEL;
+
+        if self.quests.active.is_empty() && self.quests.finished.is_empty() {
+            y += 10. * PIXEL;
+            draw_text_ex("Chưa có nhiệm vụ nào", x, y, line);
+            return;
+        }
+        for quest in &self.quests.active {
+            y += 12. * PIXEL;
+            draw_text_ex(&quest.def.name, x, y, title.clone());
+            let description = TextParams {
+                color: DARKGRAY,
+                ..line.clone()
+            };
+            for text in textwrap::wrap(&quest.def.description, 60) {
+                y += 8. * PIXEL;
+                draw_text_ex(&text, x, y, description.clone());
+            }
+            let objectives = quest.def.objectives.iter().zip(&quest.progress);
+            for (objective, progress) in objectives {
+                y += 8. * PIXEL;
+                let color = match progress >= &objective.count {
+                    true => DARKGREEN,
+                    false => BLACK,
+                };
+                let label = format!("- {} ({}/{})", objective.label(), progress, objective.count);
+                draw_text_ex(
+                    &label,
+                    x,
+                    y,
+                    TextParams {
+                        color,
+                        ..line.clone()
+                    },
+                );
+            }
+        }
+        // These are just names, there's nothing left to track
+        for id in &self.quests.finished {
+            y += 10. * PIXEL;
+            let name = match self.quest_defs.get(id) {
+                Some(def) => def.name.as_str(),
+                None => id,
+            };
+            let label = format!("{name} (xong)");
+            let params = TextParams {
+                color: GRAY,
+                ..line.clone()
+            };
+            draw_text_ex(&label, x, y, params);
+        }
+    }
+}
+
+#[rustfmt::skip]
+fn window_texture() -> Vec<Vec<u16>> {
+    vec![
+        vec![7 ,  8,  8,  8,  8,  8,  8,  8,  9],
+        vec![19, 20, 20, 20, 20, 20, 20, 20, 21],
+        vec![19, 20, 20, 20, 20, 20, 20, 20, 21],
+        vec![19, 20, 20, 20, 20, 20, 20, 20, 21],
+        vec![19, 20, 20, 20, 20, 20, 20, 20, 21],
+        vec![19, 20, 20, 20, 20, 20, 20, 20, 21],
+        vec![31, 32, 32, 32, 32, 32, 32, 32, 33],
+    ]
+}
@@ -0,0 +1,174 @@
+use macroquad::prelude::*;
+use std::rc::Rc;
+
+use crate::camera::draw_tiles;
+use crate::logic::*;
+use crate::player::PIXEL;
+
+use super::inventory::{dual_box, source_rect, window_texture};
+use super::items::{Item, ItemDef};
+
+const ROW_HEIGHT: f32 = 0.6 * TILE;
+
+/// What an NPC sells. Buying costs an item's value, selling a stack gives its value times the
+/// count back, and items worth nothing can't be bought or sold
+#[derive(Clone, Debug)]
+pub struct Shop {
+    pub stock: Vec<Rc<ItemDef>>,
+}
+
+impl Game {
+    /// Unknown items are left out, validate-maps catches those
+    pub fn open_shop(&mut self, stock: &[Rc<str>]) {
+        let stock = stock
+            .iter()
+            .filter_map(|id| match self.item_defs.get(id) {
+                Some(def) => Some(def.clone()),
+                None => {
+                    warn!("unknown item {id} in a shop");
+                    None
+                }
+            })
+            .collect();
+        self.state = GameState::GUI(GUIType::Shop(Shop { stock }));
+    }
+
+    /// False if it's too expensive, worth nothing or doesn't fit in the inventory
+    pub fn buy(&mut self, def: &Rc<ItemDef>) -> bool {
+        if def.value == 0 || self.player.coins < def.value {
+            return false;
+        }
+        let inventory = &self.player.inventory;
+        // Trying it on a copy first, so nothing has to be given back
+        let mut after = inventory.clone();
+        if after.append(Item::new(def.clone(), 1)).is_some() {
+            return false;
+        }
+        self.player.inventory = after;
+        self.player.coins -= def.value;
+        true
+    }
+
+    /// Sells the whole stack in that slot, the equipped weapon can't be sold
+    pub fn sell(&mut self, slot: usize) -> bool {
+        let item = match self.player.inventory.content[..12].get(slot) {
+            Some(Some(item)) if item.def.value > 0 => item,
+            _ => return false,
+        };
+        let price = item.def.value.saturating_mul(item.count as u32);
+        self.player.coins = self.player.coins.saturating_add(price);
+        self.player.inventory.content[slot] = None;
+        true
+    }
+
+    /// Where each item for sale goes on the left, and each inventory slot that can be sold on the
+    /// right
+    pub fn shop_rows(&self, shop: &Shop) -> (Vec<Rect>, Vec<(usize, Rect)>) {
+        let (buy_box, sell_box) = dual_box(self.cam_box().center(), 720., 1296., 50.);
+        let row = |window: Rect, index: usize| {
+            Rect::new(
+                window.x + 6. * PIXEL,
+                window.y + 20. * PIXEL + index as f32 * (ROW_HEIGHT + PIXEL),
+                window.w - 12. * PIXEL,
+                ROW_HEIGHT,
+            )
+        };
+        let buy = (0..shop.stock.len()).map(|index| row(buy_box, index));
+        let content = self.player.inventory.content[..12].iter().enumerate();
+        let sellable = content.filter_map(|(slot, item)| match item {
+            Some(item) if item.def.value > 0 => Some(slot),
+            _ => None,
+        });
+        let sell = sellable
+            .enumerate()
+            .map(|(index, slot)| (slot, row(sell_box, index)));
+        (buy.collect(), sell.collect())
+    }
+
+    pub fn tick_shop(&mut self, shop: &Shop) {
+        if !self.input.mouse_pressed(MouseButton::Left) {
+            return;
+        }
+        let mouse_pos = self.input.mouse_pos;
+        let (buy, sell) = self.shop_rows(shop);
+        if let Some(index) = buy.iter().position(|row| row.contains(mouse_pos)) {
+            self.buy(&shop.stock[index]);
+            return;
+        }
+        if let Some((slot, _)) = sell.iter().find(|(_, row)| row.contains(mouse_pos)) {
+            self.sell(*slot);
+        }
+    }
+
+    pub fn show_shop(&self, shop: &Shop) {
+        let (buy_box, sell_box) = dual_box(self.cam_box().center(), 720., 1296., 50.);
+        let texture = &self.textures["ui"];
+        draw_tiles(&window_texture(), buy_box.point(), texture, None, TILE_SIZE);
+        draw_tiles(
+            &window_texture(),
+            sell_box.point(),
+            texture,
+            None,
+            TILE_SIZE,
+        );
+
+        let params = TextParams {
+            font: self.font.as_ref(),
+            font_size: 50,
+            color: BLACK,
+            ..Default::default()
+        };
+        let title_y = buy_box.y + 15. * PIXEL;
+        draw_text_ex("Mua", buy_box.x + 8. * PIXEL, title_y, params.clone());
+        draw_text_ex("Bán", sell_box.x + 8. * PIXEL, title_y, params.clone());
+        let coins = format!("{} xu", self.player.coins);
+        draw_text_ex(&coins, sell_box.x + 40. * PIXEL, title_y, params);
+
+        let (buy, sell) = self.shop_rows(shop);
+        for (def, row) in shop.stock.iter().zip(buy) {
+            let affordable = self.player.coins >= def.value;
+            self.draw_shop_row(row, def, &def.name, def.value, affordable);
+        }
+        for (slot, row) in sell {
+            let item = self.player.inventory.content[slot].as_ref().unwrap();
+            let name = match item.count {
+                1 => item.name().to_string(),
+                count => format!("{} x{}", item.name(), count),
+            };
+            let price = item.def.value * item.count as u32;
+            self.draw_shop_row(row, &item.def, &name, price, true);
+        }
+    }
+
+    fn draw_shop_row(&self, row: Rect, def: &Rc<ItemDef>, name: &str, price: u32, active: bool) {
+        let color = match row.contains(self.input.mouse_pos) && active {
+            true => Color::new(0.3, 0.3, 0.3, 0.9),
+            false => Color::new(0., 0., 0., 0.7),
+        };
+        draw_rectangle(row.x, row.y, row.w, row.h, color);
+
+        let icon = Item::new(def.clone(), 1);
+        let params = DrawTextureParams {
+            source: source_rect(Some(&icon)),
+            dest_size: Some(vec2(row.h, row.h) * 0.8),
+            ..Default::default()
+        };
+        let padding = row.h * 0.1;
+        let texture = &self.textures["ui"];
+        draw_texture_ex(texture, row.x + padding, row.y + padding, WHITE, params);
+
+        let params = TextParams {
+            font: self.font.as_ref(),
+            font_size: 36,
+            color: match active {
+                true => WHITE,
+                false => GRAY,
+            },
+            ..Default::default()
+        };
+        let text_y = row.y + row.h / 2. + 2. * PIXEL;
+        draw_text_ex(name, row.x + row.h + PIXEL, text_y, params.clone());
+        let price = format!("{price} xu");
+        draw_text_ex(&price, row.right() - 20. * PIXEL, text_y, params);
+    }
+}
@@ -0,0 +1,297 @@
+use macroquad::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::flags::Action;
+use crate::logic::*;
+use crate::loot::{load_loot_tables, unknown_items, LootTables};
+use crate::map::*;
+use crate::monsters::definition::{load_monsters, MonsterDefs};
+use crate::monsters::spawner::parse_waves;
+use crate::npc::Movement;
+use crate::player::{Collidable, Player};
+use crate::quests::{load_quests, unknown_names, Goal, QuestDefs};
+use crate::ui::items::{load_items, ItemRegistry};
+
+// A map that loaded, along with the JSON it came from so objects can be looked at again
+struct LoadedMap {
+    file: String,
+    json: Value,
+    area: Area,
+}
+
+/// Loads every map in assets/maps the same way the game does, then checks that they all agree
+/// with each other. Returns every problem found, so an empty list means the maps are fine.
+pub fn validate_maps() -> Vec<MapLoadError> {
+    let mut errors = vec![];
+    let mut maps: HashMap<Rc<str>, LoadedMap> = HashMap::new();
+
+    let paths = match get_path("assets/maps/", ".json") {
+        Ok(paths) => paths,
+        Err(err) => return vec![err],
+    };
+    for path in paths {
+        let file = path.to_string_lossy().to_string();
+        let json_string = match read_to_string(&path) {
+            Ok(json_string) => json_string,
+            Err(err) => {
+                errors.push(MapLoadError::new(MapErrorKind::Io(err.to_string())).in_file(&file));
+                continue;
+            }
+        };
+        let (name, area) = match Area::from(&json_string, &file) {
+            Ok(map) => map,
+            Err(errs) => {
+                errors.extend(errs);
+                continue;
+            }
+        };
+        // Area::from already made sure this is valid JSON
+        let json = serde_json::from_str(&json_string).unwrap();
+        maps.insert(name, LoadedMap { file, json, area });
+    }
+
+    let monsters = match load_monsters() {
+        Ok(monsters) => monsters,
+        Err(errs) => {
+            errors.extend(errs);
+            MonsterDefs::new()
+        }
+    };
+
+    let loot = match load_loot_tables() {
+        Ok(loot) => loot,
+        Err(errs) => {
+            errors.extend(errs);
+            LootTables::new()
+        }
+    };
+    let items = match load_items() {
+        Ok(items) => items,
+        Err(errs) => {
+            errors.extend(errs);
+            ItemRegistry::new()
+        }
+    };
+    errors.extend(unknown_items(&loot, &items));
+
+    let quests = match load_quests() {
+        Ok(quests) => quests,
+        Err(errs) => {
+            errors.extend(errs);
+            QuestDefs::new()
+        }
+    };
+    let map_names: Vec<&str> = maps.keys().map(|name| &**name).collect();
+    errors.extend(unknown_names(&quests, &items, &monsters, &map_names));
+    // Nobody to talk to means the quest can never be done
+    for quest in quests.values() {
+        for objective in &quest.objectives {
+            let npc = match &objective.goal {
+                Goal::Talk(npc) => npc,
+                _ => continue,
+            };
+            let npcs = maps.values().flat_map(|map| &map.area.npcs);
+            if npcs.clone().any(|other| other.name == *npc) {
+                continue;
+            }
+            let kind = MapErrorKind::UnknownNpc(npc.to_string());
+            let file = format!("assets/quests/{}.json", quest.id);
+            errors.push(MapLoadError::new(kind).in_file(&file));
+        }
+    }
+
+    // Monsters only have the name of their loot table
+    for def in monsters.values() {
+        match &def.loot {
+            Some(table) if !loot.contains_key(table) => {
+                let kind = MapErrorKind::UnknownLoot(table.to_string());
+                let file = format!("assets/monsters/{}.json", def.name);
+                errors.push(MapLoadError::new(kind).in_file(&file));
+            }
+            _ => (),
+        }
+    }
+
+    for map in maps.values() {
+        errors.extend(check_map(map, &maps, &monsters, &loot, &items, &quests));
+    }
+    errors.sort_by(|a, b| (&a.file, &a.layer, a.object).cmp(&(&b.file, &b.layer, b.object)));
+    errors
+}
+
+fn check_map(
+    map: &LoadedMap,
+    maps: &HashMap<Rc<str>, LoadedMap>,
+    monsters: &MonsterDefs,
+    loot: &LootTables,
+    items: &ItemRegistry,
+    quests: &QuestDefs,
+) -> Vec<MapLoadError> {
+    let mut errors = vec![];
+    let layers = map.json["layers"].as_array().cloned().unwrap_or_default();
+
+    // Dialogues that check for items nobody can ever have, and shops selling them
+    for npc in &map.area.npcs {
+        let at_npc = |kind| {
+            MapLoadError::new(kind)
+                .in_file(&map.file)
+                .in_layer("NPCs")
+                .in_object(Some(npc.id))
+        };
+        for item in npc
+            .dialogue
+            .items()
+            .into_iter()
+            .chain(npc.shop.iter().map(|id| &**id))
+        {
+            if items.contains_key(item) {
+                continue;
+            }
+            errors.push(at_npc(MapErrorKind::UnknownItem(item.to_string())));
+        }
+        for quest in npc.dialogue.quests() {
+            if quests.contains_key(quest) {
+                continue;
+            }
+            errors.push(at_npc(MapErrorKind::UnknownQuest(quest.to_string())));
+        }
+        // The game only has the textures in res/
+        if !Path::new(&format!("res/{}.png", npc.sprite)).exists() {
+            errors.push(at_npc(MapErrorKind::UnknownSprite(npc.sprite.to_string())));
+        }
+        let nodes = npc.dialogue.nodes.iter();
+        let opens_shop = nodes
+            .flat_map(|node| &node.actions)
+            .any(|action| *action == Action::OpenShop);
+        // Patrols don't try to get around walls, so every stop has to be clear
+        if let Movement::Patrol { points, .. } = &npc.movement {
+            let name = map.json["class"].as_str().unwrap_or_default();
+            for point in points {
+                let hitbox = npc.hitbox.offset(*point - npc.hitbox.center());
+                if !map
+                    .area
+                    .walls
+                    .near(hitbox)
+                    .any(|wall| wall.overlaps(&hitbox))
+                {
+                    continue;
+                }
+                errors.push(at_npc(MapErrorKind::InsideWall(name.to_string(), *point)));
+            }
+        }
+        if opens_shop && npc.shop.is_empty() {
+            errors.push(at_npc(MapErrorKind::Field("shop")));
+        }
+        // Items worth nothing would be free, so they can't be bought either
+        for def in npc.shop.iter().filter_map(|id| items.get(id)) {
+            if def.value == 0 {
+                errors.push(at_npc(MapErrorKind::FreeItem(def.id.to_string())));
+            }
+        }
+    }
+
+    for layer in &layers {
+        let layer_name = layer["name"].as_str().unwrap_or_default();
+        let objects = layer["objects"].as_array().cloned().unwrap_or_default();
+        for object in &objects {
+            // make_interactable doesn't care about case either
+            let kind = object["type"].as_str().unwrap_or_default().to_lowercase();
+            let result = match layer_name.to_lowercase().as_str() {
+                "gates" => check_destination(object, maps),
+                "interactables" => match kind.as_str() {
+                    "door" => check_destination(object, maps),
+                    "chest" => check_chest(object, loot, items),
+                    _ => Ok(()),
+                },
+                "spawners" | "bosses" => check_spawner(object, monsters),
+                _ => Ok(()),
+            };
+            if let Err(kind) = result {
+                let err = MapLoadError::new(kind)
+                    .in_file(&map.file)
+                    .in_layer(layer_name)
+                    .in_object(object["id"].as_u64());
+                errors.push(err);
+            }
+        }
+    }
+    errors
+}
+
+// Both gates and doors send the player to "<map> <x> <y>"
+fn check_destination(
+    object: &Value,
+    maps: &HashMap<Rc<str>, LoadedMap>,
+) -> Result<(), MapErrorKind> {
+    let command = get_command(&object["properties"]).ok_or(MapErrorKind::NoCommand)?;
+    let (name, pos) = parse_command(command)?;
+    let target = match maps.get(name) {
+        Some(target) => &target.area,
+        None => return Err(MapErrorKind::UnknownMap(name.to_string())),
+    };
+
+    let terrain = target.draw_mesh.terrain();
+    let bounds = Rect::new(
+        0.,
+        0.,
+        terrain[0].len() as f32 * TILE,
+        terrain.len() as f32 * TILE,
+    );
+    if !bounds.contains(pos) {
+        return Err(MapErrorKind::OutOfBounds(name.to_string(), pos));
+    }
+
+    // Where the player would actually be standing once they get there
+    let mut player = Player::new(name.into());
+    player.props.pos = pos;
+    let hitbox = player.hitbox();
+    if target.walls.near(hitbox).any(|wall| wall.overlaps(&hitbox)) {
+        return Err(MapErrorKind::InsideWall(name.to_string(), pos));
+    }
+    Ok(())
+}
+
+fn check_chest(
+    object: &Value,
+    loot: &LootTables,
+    items: &ItemRegistry,
+) -> Result<(), MapErrorKind> {
+    if let Some(table) = get_property(object, "loot") {
+        return match loot.contains_key(table) {
+            true => Ok(()),
+            false => Err(MapErrorKind::UnknownLoot(table.to_string())),
+        };
+    }
+    match get_item(object) {
+        Ok(item) if items.contains_key(&item) => Ok(()),
+        Ok(item) => Err(MapErrorKind::UnknownItem(item.to_string())),
+        Err(ItemErr::NoKey) | Err(ItemErr::NotSameType) => Err(MapErrorKind::Field("item")),
+    }
+}
+
+fn check_spawner(object: &Value, monsters: &MonsterDefs) -> Result<(), MapErrorKind> {
+    // Area::from already made sure these parse
+    if let Some(waves) = get_property(object, "waves") {
+        for wave in parse_waves(waves)? {
+            for (kind, _) in wave {
+                if !monsters.contains_key(&kind) {
+                    return Err(MapErrorKind::UnknownMob(kind.to_string()));
+                }
+            }
+        }
+        return Ok(());
+    }
+    // Spawners with no type are slime spawners
+    let kind = match object["type"].as_str() {
+        Some(kind) if !kind.is_empty() => kind,
+        _ => return Ok(()),
+    };
+    match monsters.contains_key(kind) {
+        true => Ok(()),
+        false => Err(MapErrorKind::UnknownMob(kind.to_string())),
+    }
+}
@@ -1,26 +1,31 @@
 use crate::logic::TILE;
+use serde_json::Value;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Weapon {
     pub base_damage: f32,
     pub lenght: f32,
     pub cooldown: f32,
 }
 
-impl Weapon {
-    pub fn rusty_sword() -> Self {
+impl Default for Weapon {
+    // Only matters until a real weapon is equipped, attacking needs one anyway
+    fn default() -> Self {
         Weapon {
             base_damage: 10.,
             lenght: TILE,
             cooldown: 0.3,
         }
     }
+}
 
-    pub fn black_sword() -> Self {
-        Weapon {
-            base_damage: 20.,
-            lenght: TILE * 1.2,
-            cooldown: 0.4,
-        }
+impl Weapon {
+    /// The "weapon" table of an item, length is in tiles
+    pub fn from_json(json: &Value) -> Option<Self> {
+        Some(Weapon {
+            base_damage: json["damage"].as_f64()? as f32,
+            lenght: json["length"].as_f64().unwrap_or(1.) as f32 * TILE,
+            cooldown: json["cooldown"].as_f64()? as f32,
+        })
     }
 }
@@ -0,0 +1,31 @@
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use game::input::InputFrame;
+use game::map::Area;
+use macroquad::prelude::*;
+
+/// A 20 by 20 map of open ground. layers are the rest of the layers after the terrain, as JSON
+pub fn open_map(class: &str, layers: &str) -> Area {
+    let terrain = vec!["1"; 400].join(",");
+    let layers = match layers {
+        "" => String::new(),
+        layers => format!(", {layers}"),
+    };
+    let map = format!(
+        r#"{{ "class": "{class}", "layers": [
+        {{ "name": "Terrain", "type": "tilelayer", "width": 20, "data": [{terrain}] }}{layers}
+    ] }}"#
+    );
+    let (_, area) = Area::from(&map, &format!("{}.json", class.to_lowercase())).unwrap();
+    area
+}
+
+/// Presses R on the first interactable, for long enough that a chest opens in one go. Returns
+/// whether it ended up opened
+pub fn try_chest(area: &mut Area) -> bool {
+    let chest = &mut area.interactables[0];
+    let search_box = chest.hitbox();
+    chest.activate(&search_box, &InputFrame::new(1.).press(KeyCode::R));
+    chest.is_opened()
+}
@@ -0,0 +1,118 @@
+use game::dialogue::{Branch, Dialogue};
+use game::flags::World;
+use game::headless::Headless;
+use game::input::InputFrame;
+use game::logic::GameState;
+use game::timestep::FIXED_DT;
+use macroquad::prelude::*;
+use serde_json::json;
+
+#[test]
+fn flat_dialogs_are_one_branch() {
+    let dialogue = Dialogue::from_json(&json!({ "dialog": ["Xin chào", "Tạm biệt"] })).unwrap();
+    let world = World {
+        flags: &Default::default(),
+        inventory: &game::ui::inventory::Inventory::empty(),
+        quests: &Default::default(),
+    };
+    assert_eq!(dialogue.nodes.len(), 2);
+    let start = Branch::pick(&dialogue.start, world).unwrap();
+    assert_eq!(start, 0);
+    assert_eq!(Branch::pick(&dialogue.nodes[0].next, world), Some(1));
+    assert!(dialogue.nodes[1].next.is_empty());
+    assert!(dialogue.nodes.iter().all(|node| node.choices.is_empty()));
+}
+
+#[test]
+fn choices_must_lead_somewhere_real() {
+    let broken = json!({
+        "start": "hi",
+        "nodes": { "hi": { "text": "?", "choices": [{ "text": "!", "next": "nowhere" }] } }
+    });
+    let err = Dialogue::from_json(&broken).unwrap_err();
+    assert!(err.contains("nowhere"), "{err}");
+    assert!(Dialogue::from_json(&json!({ "nodes": {} })).is_err());
+}
+
+#[test]
+fn picking_a_choice_follows_it() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let mut sim = Headless::new().unwrap();
+    sim.game.current_map = "Forest".into();
+    let forest = sim.game.maps.get_mut("Forest").unwrap();
+    let npc = forest
+        .npcs
+        .iter_mut()
+        .find(|npc| &*npc.name == "bob")
+        .unwrap();
+    npc.is_talking = true;
+    let dialogue = npc.dialogue.clone();
+    sim.game.start_dialogue();
+
+    let idle = InputFrame::new(FIXED_DT);
+    // Nowhere near the choices
+    let click = InputFrame::new(FIXED_DT).click(MouseButton::Left, vec2(-1e6, -1e6));
+    // First node has no choices, a click skips the typing and the next moves on
+    sim.step(click.clone());
+    sim.step(click.clone());
+    sim.run(&idle, 120);
+    let GameState::Talking(node, _) = sim.game.state else {
+        panic!("stopped talking too early")
+    };
+    let ask = &dialogue.nodes[node];
+    assert_eq!(ask.choices.len(), 2);
+
+    // Clicking away from the choices does nothing
+    sim.step(click);
+    assert!(matches!(sim.game.state, GameState::Talking(n, _) if n == node));
+
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Key2));
+    let GameState::Talking(bye, _) = sim.game.state else {
+        panic!("the choice ended the conversation")
+    };
+    assert_eq!(Some(bye), ask.choices[1].next[0].to);
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Key1));
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Key1));
+    assert!(matches!(sim.game.state, GameState::Normal));
+}
+
+#[test]
+fn conditions_and_actions_use_the_flags() {
+    use game::flags::*;
+    use game::ui::inventory::Inventory;
+
+    let dialogue = Dialogue::from_json(&json!({
+        "start": [{ "if": ["flag met", "not has slime 2"], "next": "again" }, { "next": "hello" }],
+        "nodes": {
+            "hello": { "text": "", "do": ["set met", "add talks 2"] },
+            "again": { "text": "" }
+        }
+    }))
+    .unwrap();
+    let hello = dialogue
+        .nodes
+        .iter()
+        .position(|node| !node.actions.is_empty());
+    let mut flags = Flags::default();
+    let inventory = Inventory::empty();
+    let quests = Default::default();
+    let world = World {
+        flags: &flags,
+        inventory: &inventory,
+        quests: &quests,
+    };
+    assert_eq!(Branch::pick(&dialogue.start, world), hello);
+
+    for action in &dialogue.nodes[hello.unwrap()].actions {
+        flags.run(action)
+    }
+    assert_eq!(flags.get("talks"), 2);
+    let world = World {
+        flags: &flags,
+        inventory: &inventory,
+        quests: &quests,
+    };
+    assert_ne!(Branch::pick(&dialogue.start, world), hello);
+    assert!(Condition::parse("counter talks >= 2").unwrap().check(world));
+    assert!(Condition::parse("counter talks ~ 2").is_err());
+}
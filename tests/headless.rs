@@ -0,0 +1,114 @@
+use game::headless::*;
+use game::input::InputFrame;
+use game::timestep::FIXED_DT;
+use game::ui::items::Item;
+use macroquad::prelude::*;
+
+// The game loads assets relative to the working directory, which for tests is the crate root
+fn setup() -> Headless {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    Headless::new().unwrap()
+}
+
+#[test]
+fn walk_through_forest_gate_into_village() {
+    let mut sim = setup();
+    sim.game.current_map = "Forest".into();
+    sim.game.player.props.pos = vec2(1., 15.) * game::logic::TILE;
+
+    let walk_left = InputFrame::new(FIXED_DT).hold(KeyCode::A);
+    let arrived = sim.run_until(&walk_left, 600, |game| &*game.current_map == "Village");
+    assert!(arrived, "player never left the Forest");
+
+    // Let the transition finish
+    sim.run(&InputFrame::new(FIXED_DT), 60);
+    assert!(matches!(sim.game.state, game::logic::GameState::Normal));
+}
+
+#[test]
+fn save_round_trip() {
+    let mut sim = setup();
+    sim.game.current_map = "House".into();
+    sim.game.player.props.health = 42.;
+    sim.game.flags.set("dungeon_cleared", 1);
+    let defs = &sim.game.item_defs;
+    let slime = Item::from_key(defs, "slime", 3).unwrap();
+    let black_sword = Item::from_key(defs, "black_sword", 1).unwrap();
+    sim.game.player.inventory.append(slime);
+    sim.game.player.inventory.content[12] = Some(black_sword);
+    for chest in sim
+        .game
+        .maps
+        .get_mut("House")
+        .unwrap()
+        .interactables
+        .iter_mut()
+    {
+        chest.set_opened(true);
+    }
+    let save = sim.game.to_save();
+
+    let mut other = setup();
+    other.game.apply_save(&save).unwrap();
+    assert_eq!(&*other.game.current_map, "House");
+    assert_eq!(other.game.player.props.health, 42.);
+    assert!(other.game.flags.is_set("dungeon_cleared"));
+    assert_eq!(
+        other.game.player.inventory.content[0]
+            .as_ref()
+            .unwrap()
+            .count,
+        3
+    );
+    assert_eq!(other.game.player.held_weapon.base_damage, 20.);
+    let house = &other.game.maps["House"];
+    assert!(house.interactables.iter().all(|chest| chest.is_opened()));
+}
+
+#[test]
+fn save_keeps_items_off_the_inventory() {
+    let mut sim = setup();
+    let defs = &sim.game.item_defs;
+    let mushroom = Item::from_key(defs, "mushroom", 1).unwrap();
+    let black_sword = Item::from_key(defs, "black_sword", 1).unwrap();
+    // No free slot for the held item to go back into
+    for slot in sim.game.player.inventory.content[..12].iter_mut() {
+        *slot = Some(mushroom.clone());
+    }
+    let slime = Item::from_key(defs, "slime", 2).unwrap();
+    sim.game.player.inventory.set_holding(Some(slime));
+    let house = sim.game.maps.get_mut("House").unwrap();
+    let dropped = game::ui::items::ItemEntity::new(black_sword, vec2(300., 400.));
+    house.items.push(dropped);
+    // Saved halfway through opening, before the chest gave anything
+    let chest = &mut house.interactables[0];
+    let search_box = chest.hitbox();
+    chest.activate(&search_box, &InputFrame::new(FIXED_DT).press(KeyCode::R));
+    assert!(!chest.is_opened());
+    let save = sim.game.to_save();
+
+    let mut other = setup();
+    other.game.apply_save(&save).unwrap();
+    let holding = other.game.player.inventory.holding().unwrap();
+    assert_eq!((holding.id(), holding.count), ("slime", 2));
+    let house = &other.game.maps["House"];
+    assert!(!house.interactables[0].is_opened());
+    let [item] = &house.items[..] else {
+        panic!("expected one item on the ground, got {}", house.items.len())
+    };
+    assert_eq!(item.item.id(), "black_sword");
+    assert_eq!(item.hitbox.point(), vec2(300., 400.));
+}
+
+#[test]
+fn saved_stacks_fit_in_a_slot() {
+    let mut sim = setup();
+    let mut save = sim.game.to_save();
+    save["player"]["inventory"][0] = serde_json::json!({ "item": "slime", "count": 300 });
+    save["player"]["inventory"][1] = serde_json::json!({ "item": "slime", "count": 0 });
+    sim.game.apply_save(&save).unwrap();
+    let content = &sim.game.player.inventory.content;
+    let max_stack = sim.game.item_defs["slime"].max_stack;
+    assert_eq!(content[0].as_ref().unwrap().count, max_stack);
+    assert_eq!(content[1].as_ref().unwrap().count, 1);
+}
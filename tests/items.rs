@@ -0,0 +1,86 @@
+use game::logic::TILE;
+use game::map::MapErrorKind;
+use game::ui::inventory::Inventory;
+use game::ui::items::*;
+use serde_json::json;
+use std::rc::Rc;
+
+#[test]
+fn shipped_items_load() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let defs = load_items().unwrap();
+    let sword = &defs["black_sword"];
+    assert_eq!(sword.class, ItemType::Weapon);
+    let weapon = sword.weapon.as_ref().unwrap();
+    assert_eq!(weapon.base_damage, 20.);
+    assert_eq!(weapon.lenght, TILE * 1.2);
+    assert!(defs["slime"].weapon.is_none());
+}
+
+#[test]
+fn broken_items_are_rejected() {
+    let no_stats = json!({
+        "name": "Kiếm", "description": "", "icon": [0, 0, 24, 24], "class": "weapon"
+    });
+    let err = ItemDef::from_json("sword", &no_stats).unwrap_err();
+    assert!(matches!(err, MapErrorKind::Field("weapon")));
+    let bad_icon = json!({ "name": "", "description": "", "icon": [0, 0], "class": "item" });
+    let err = ItemDef::from_json("thing", &bad_icon).unwrap_err();
+    assert!(matches!(err, MapErrorKind::Field("icon")));
+}
+
+#[test]
+fn stacks_stop_at_max_stack() {
+    let json = json!({
+        "name": "Đá", "description": "", "icon": [0, 0, 24, 24], "class": "item", "max_stack": 5
+    });
+    let rock = Rc::new(ItemDef::from_json("rock", &json).unwrap());
+    let mut inv = Inventory::empty();
+    assert!(inv.append(Item::new(rock.clone(), 3)).is_none());
+    assert!(inv.append(Item::new(rock.clone(), 4)).is_none());
+    assert_eq!(inv.content[0].as_ref().unwrap().count, 5);
+    assert_eq!(inv.content[1].as_ref().unwrap().count, 2);
+
+    // Only room for 53 more
+    let rest = inv.append(Item::new(rock, 60)).unwrap();
+    assert_eq!(rest.count, 7);
+    assert!(inv.content[..12]
+        .iter()
+        .all(|slot| slot.as_ref().unwrap().count == 5));
+    assert!(inv.content[12].is_none());
+}
+
+#[test]
+fn consumables_apply_and_use_up() {
+    use game::effects::BuffKind;
+    use game::player::Player;
+
+    let json = json!({
+        "name": "Thuốc", "description": "", "icon": [0, 0, 24, 24], "class": "consumable",
+        "max_stack": 9,
+        "effects": [
+            { "type": "heal", "amount": 30 },
+            { "type": "damage", "multiplier": 2, "duration": 5 },
+            { "type": "cure" }
+        ]
+    });
+    let potion = Rc::new(ItemDef::from_json("potion", &json).unwrap());
+    let mut player = Player::new("Room".into());
+    player.props.health = 80.;
+    // Something bad to cure
+    player.apply(&game::effects::Effect::Buff(BuffKind::Speed, 0.5, 5.));
+    player.inventory.append(Item::new(potion, 2));
+
+    assert!(player.use_slot(0));
+    assert_eq!(player.props.health, 100.);
+    assert_eq!(player.multiplier(BuffKind::Damage), 2.);
+    assert_eq!(player.multiplier(BuffKind::Speed), 1.);
+    assert_eq!(player.inventory.content[0].as_ref().unwrap().count, 1);
+
+    assert!(player.use_slot(0));
+    assert!(player.inventory.content[0].is_none());
+    assert!(!player.use_slot(0));
+
+    player.tick_buffs(6.);
+    assert_eq!(player.multiplier(BuffKind::Damage), 1.);
+}
@@ -0,0 +1,115 @@
+use game::loot::*;
+use game::map::MapErrorKind;
+use game::monsters::definition::MonsterDef;
+use game::monsters::mob::Mob;
+use game::monsters::IsAMonster;
+use game::ui::items::{load_items, ItemRegistry};
+use macroquad::prelude::*;
+use serde_json::json;
+use std::rc::Rc;
+
+mod common;
+
+fn item_defs() -> ItemRegistry {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    load_items().unwrap()
+}
+
+#[test]
+fn shipped_loot_tables_load() {
+    let defs = item_defs();
+    let tables = load_loot_tables().unwrap();
+    assert_eq!(tables["slime_king"].guaranteed.len(), 1);
+    assert!(unknown_items(&tables, &defs).is_empty());
+}
+
+#[test]
+fn rolls_respect_weights_and_counts() {
+    let json = json!({
+        "guaranteed": [{ "item": "slime", "count": [2, 4] }],
+        "rolls": 3,
+        "nothing": 0,
+        "entries": [
+            { "item": "mushroom", "weight": 1, "count": 2 },
+            { "item": "rusty_sword", "weight": 0 }
+        ]
+    });
+    let defs = item_defs();
+    let table = LootTable::from_json(&json).unwrap();
+    for _ in 0..50 {
+        let items = table.roll(&defs);
+        assert_eq!(items.len(), 4);
+        assert!((2..=4).contains(&items[0].count));
+        // Weight 0 never comes up, and nothing has no weight either
+        assert!(items[1..].iter().all(|item| item.id() == "mushroom"));
+        assert!(items[1..].iter().all(|item| item.count == 2));
+    }
+
+    let empty = LootTable::from_json(&json!({ "nothing": 1, "entries": [] })).unwrap();
+    assert!(empty.roll(&defs).is_empty());
+
+    let bad = json!({ "entries": [{ "item": "gold_bar" }] });
+    let tables = LootTables::from([("bad".into(), LootTable::from_json(&bad).unwrap())]);
+    let errors = unknown_items(&tables, &defs);
+    assert!(matches!(&errors[..], [err] if matches!(err.kind, MapErrorKind::UnknownItem(_))));
+    let backwards = json!({ "entries": [{ "item": "slime", "count": [3, 1] }] });
+    assert!(LootTable::from_json(&backwards).is_err());
+}
+
+#[test]
+fn extreme_counts_and_weights() {
+    let defs = item_defs();
+    let widest = json!({ "guaranteed": [{ "item": "slime", "count": [0, 255] }] });
+    let table = LootTable::from_json(&widest).unwrap();
+    for _ in 0..50 {
+        table.roll(&defs);
+    }
+
+    let heavy = json!({ "entries": [
+        { "item": "slime", "weight": 4000000000u64 },
+        { "item": "mushroom", "weight": 4000000000u64 }
+    ] });
+    assert!(LootTable::from_json(&heavy).is_err());
+    let too_heavy = json!({ "entries": [{ "item": "slime", "weight": 5000000000u64 }] });
+    assert!(LootTable::from_json(&too_heavy).is_err());
+}
+
+#[test]
+fn dead_monsters_drop_everything_they_rolled() {
+    let mut area = common::open_map("Field", "");
+    let def = json!({
+        "texture": "slime",
+        "health": 10,
+        "speed": 80,
+        "tracking_range": 400,
+        "damage": 4,
+        "hitbox": [0, 0, 24, 24],
+        "frame_size": [24, 24],
+        "animations": {
+            "idle": { "row": 0, "frames": 4, "fps": 8 },
+            "moving": { "row": 1, "frames": 6, "fps": 8 }
+        },
+        "loot": "pile"
+    });
+    let def = Rc::new(MonsterDef::from_json("blob", &def).unwrap());
+    let mut mob = Mob::new(def, vec2(1000., 1000.));
+    mob.get_mut_props().should_despawn = true;
+    area.enemies.push(Box::new(mob));
+
+    let pile = json!({ "guaranteed": [
+        { "item": "slime" }, { "item": "mushroom" }, { "item": "black_sword" }
+    ] });
+    let tables = LootTables::from([("pile".into(), LootTable::from_json(&pile).unwrap())]);
+    area.clean_up(&tables, &item_defs());
+
+    assert!(area.enemies.is_empty());
+    assert_eq!(area.items.len(), 3);
+    // Spread out, but still around where the monster was
+    let corpse = Rect::new(1000., 1000., 144., 144.).center();
+    for (i, a) in area.items.iter().enumerate() {
+        assert!(a.hitbox.center().distance(corpse) <= game::logic::TILE);
+        for b in &area.items[i + 1..] {
+            assert!(a.hitbox.center().distance(b.hitbox.center()) > 1.);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use game::map::*;
+use game::tileset::find_tileset;
+use macroquad::prelude::*;
+
+const BROKEN_MAP: &str = r#"{
+    "class": "Broken",
+    "layers": [
+        { "name": "Terrain", "type": "tilelayer", "width": 2, "data": [1, 2, 3, 4] },
+        { "name": "Gates", "type": "objectgroup", "objects": [
+            { "id": 7, "x": 0, "y": 0, "width": 16, "height": 16,
+              "properties": [{ "name": "to", "type": "string", "value": "Village 48" }] },
+            { "id": 8, "x": 0, "y": 0, "width": 16, "height": 16 }
+        ] },
+        { "name": "Interactables", "type": "objectgroup", "objects": [
+            { "id": 9, "x": 0, "y": 0, "type": "barrel" }
+        ] }
+    ]
+}"#;
+
+#[test]
+fn broken_map_reports_every_error() {
+    let errors = match Area::from(BROKEN_MAP, "broken.json") {
+        Ok(_) => panic!("broken map loaded"),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors.len(), 3);
+    assert!(errors.iter().all(|err| &*err.file == "broken.json"));
+
+    let bad_command = &errors[0];
+    assert_eq!(bad_command.layer.as_deref(), Some("Gates"));
+    assert_eq!(bad_command.object, Some(7));
+    assert!(matches!(bad_command.kind, MapErrorKind::BadCommand(_)));
+    assert!(matches!(errors[1].kind, MapErrorKind::NoCommand));
+    assert!(matches!(
+        errors[2].kind,
+        MapErrorKind::UnknownInteractable(_)
+    ));
+}
+
+#[test]
+fn missing_fields_are_errors() {
+    let map = r#"{ "class": "Fields", "layers": [
+        { "name": "Terrain", "type": "tilelayer", "width": 2, "data": [1, 2, 3, 4] },
+        { "name": "Walls", "type": "objectgroup", "objects": [{ "id": 3, "x": 0, "y": 0 }] },
+        { "name": "Interactables", "type": "objectgroup", "objects": [
+            { "id": 4, "x": 0, "y": "left", "type": "chest",
+              "properties": [{ "name": "item", "type": "string", "value": "slime" }] },
+            { "id": 5, "x": 0, "y": 0, "type": "chest" }
+        ] }
+    ] }"#;
+    let errors = Area::from(map, "fields.json").err().unwrap();
+    let fields: Vec<_> = errors.iter().map(|err| (err.object, &err.kind)).collect();
+    assert!(matches!(
+        &fields[..],
+        [
+            (Some(3), MapErrorKind::Field("width")),
+            (Some(4), MapErrorKind::Field("y")),
+            (Some(5), MapErrorKind::Field("item")),
+        ]
+    ));
+
+    let err = game::logic::get_path("no/such/folder/", ".json").unwrap_err();
+    assert!(matches!(err.kind, MapErrorKind::Io(_)));
+}
+
+#[test]
+fn invalid_json_is_an_error() {
+    let errors = Area::from("{ not json", "bad.json").err().unwrap();
+    assert!(matches!(errors[0].kind, MapErrorKind::Json(_)));
+}
+
+const TILESET_MAP: &str = r#"{
+    "class": "Tilesets",
+    "tilesets": [
+        { "firstgid": 1, "columns": 12, "tilecount": 348, "tilewidth": 16, "tileheight": 16,
+          "image": "terrain.png", "margin": 0, "spacing": 0 },
+        { "firstgid": 349, "source": "tilesets/trees.tsx" }
+    ],
+    "layers": [
+        { "name": "Terrain", "type": "tilelayer", "width": 2, "data": [1, 13, 349, 352] }
+    ]
+}"#;
+
+const TREES_TSX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="trees" tilewidth="32" tileheight="48" spacing="2" tilecount="6" columns="3">
+ <image source="trees.png" width="100" height="98"/>
+ <tile id="1">
+  <animation>
+   <frame tileid="1" duration="100"/>
+   <frame tileid="2" duration="300"/>
+  </animation>
+ </tile>
+</tileset>
+"#;
+
+// Puts the files TILESET_MAP needs in a temporary folder, returns where the map would be.
+// Every test gets its own folder since they run in parallel
+fn tileset_dir(test: &str) -> std::path::PathBuf {
+    let name = format!("rpg-tileset-test-{}-{test}", std::process::id());
+    let dir = std::env::temp_dir().join(name);
+    std::fs::create_dir_all(dir.join("tilesets")).unwrap();
+    std::fs::write(dir.join("terrain.png"), "").unwrap();
+    std::fs::write(dir.join("tilesets/trees.png"), "").unwrap();
+    std::fs::write(dir.join("tilesets/trees.tsx"), TREES_TSX).unwrap();
+    dir.join("tilesets.json")
+}
+
+#[test]
+fn tile_ids_resolve_across_tilesets() {
+    let file = tileset_dir("tile_ids_resolve_across_tilesets");
+
+    let (_, area) = Area::from(TILESET_MAP, file.to_str().unwrap()).unwrap();
+    let tilesets = &area.tilesets;
+    assert_eq!(tilesets.len(), 2);
+
+    let terrain = find_tileset(tilesets, 13).unwrap();
+    assert_eq!(&*terrain.texture, "terrain");
+    assert_eq!(terrain.source_rect(13), Rect::new(0., 16., 16., 16.));
+
+    let trees = find_tileset(tilesets, 352).unwrap();
+    assert_eq!(&*trees.texture, "trees");
+    assert_eq!(trees.tile_size, vec2(32., 48.));
+    assert_eq!(trees.source_rect(352), Rect::new(0., 50., 32., 48.));
+
+    assert!(find_tileset(tilesets, 0).is_none());
+    assert!(find_tileset(tilesets, 355).is_none());
+}
+
+#[test]
+fn missing_tileset_is_an_error() {
+    let map = TILESET_MAP.replace("tilesets/trees.tsx", "nowhere.tsx");
+    let errors = Area::from(&map, "nowhere/tilesets.json").err().unwrap();
+    assert!(matches!(errors[0].kind, MapErrorKind::Tileset(_)));
+}
+
+#[test]
+fn flip_flags_survive_loading() {
+    let map = TILESET_MAP.replace("[1, 13, 349, 352]", "[1, 2147483661, 1610613085, 0]");
+    let file = tileset_dir("flip_flags_survive_loading");
+
+    let (_, area) = Area::from(&map, file.to_str().unwrap()).unwrap();
+    let terrain = area.draw_mesh.terrain();
+
+    let flipped = terrain[0][1];
+    assert_eq!(flipped.gid, 13);
+    assert!(flipped.flip_x && !flipped.flip_y && !flipped.flip_diagonal);
+    assert_eq!(flipped.transform(), (0., true, false));
+
+    // Vertical and diagonal together is a quarter turn anticlockwise
+    let rotated = terrain[1][0];
+    assert_eq!(rotated.gid, 349);
+    assert_eq!(
+        rotated.transform(),
+        (-std::f32::consts::FRAC_PI_2, false, false)
+    );
+    assert!(terrain[1][1].is_empty());
+}
+
+#[test]
+fn animated_tiles_follow_the_clock() {
+    let file = tileset_dir("animated_tiles_follow_the_clock");
+    let (_, area) = Area::from(TILESET_MAP, file.to_str().unwrap()).unwrap();
+    let trees = find_tileset(&area.tilesets, 350).unwrap();
+
+    assert_eq!(trees.animated_gid(350, 0.05), 350);
+    assert_eq!(trees.animated_gid(350, 0.2), 351);
+    // Loops back around after 400ms
+    assert_eq!(trees.animated_gid(350, 0.45), 350);
+    // Tiles without an animation never change
+    assert_eq!(trees.animated_gid(349, 0.2), 349);
+}
+
+#[test]
+fn tile_layers_keep_tiled_order() {
+    let layer = |name: &str, extra: &str| {
+        format!(r#"{{ "name": "{name}", "type": "tilelayer", "width": 1, "data": [1] {extra} }}"#)
+    };
+    let props = r#", "properties": [{ "name": "entities", "type": "bool", "value": true }]"#;
+    let layers = [
+        layer("Terrain", ""),
+        layer("Trees", props),
+        layer("Roofs", ""),
+    ];
+    let map = format!(
+        r#"{{ "class": "Layers", "layers": [{}] }}"#,
+        layers.join(",")
+    );
+
+    let (_, area) = Area::from(&map, "layers.json").unwrap();
+    let names: Vec<&str> = area
+        .draw_mesh
+        .layers
+        .iter()
+        .map(|layer| &*layer.name)
+        .collect();
+    assert_eq!(names, ["Terrain", "Trees", "Roofs"]);
+    assert_eq!(area.draw_mesh.entity_layer, 1);
+
+    // Without one, entities go right above Terrain like they always have
+    let map = format!(
+        r#"{{ "class": "Old", "layers": [{}, {}] }}"#,
+        layers[0], layers[2]
+    );
+    let (_, area) = Area::from(&map, "old.json").unwrap();
+    let names: Vec<&str> = area
+        .draw_mesh
+        .layers
+        .iter()
+        .map(|layer| &*layer.name)
+        .collect();
+    assert_eq!(names, ["Terrain", "Entities", "Roofs"]);
+    assert_eq!(area.draw_mesh.entity_layer, 1);
+}
@@ -0,0 +1,343 @@
+use game::monsters::behaviour::*;
+use game::monsters::definition::*;
+use game::monsters::mob::Mob;
+use game::monsters::IsAMonster;
+use game::player::Collidable;
+use macroquad::prelude::*;
+use serde_json::json;
+use std::rc::Rc;
+
+mod common;
+
+#[test]
+fn shipped_monsters_load() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let monsters = load_monsters().unwrap();
+    assert_eq!(monsters["slime"].health, 50.);
+    assert_eq!(monsters["mushroom"].speed, 100.);
+}
+
+fn blob_json() -> serde_json::Value {
+    json!({
+        "texture": "slime",
+        "health": 30,
+        "speed": 80,
+        "tracking_range": 400,
+        "damage": 4,
+        "hitbox": [2, 4, 20, 20],
+        "frame_size": [24, 24],
+        "animations": {
+            "idle": { "row": 0, "frames": 4, "fps": 8 },
+            "moving": { "row": 1, "frames": 6, "fps": 8 }
+        }
+    })
+}
+
+#[test]
+fn monsters_are_built_from_their_definition() {
+    let def = Rc::new(MonsterDef::from_json("blob", &blob_json()).unwrap());
+    assert_eq!(def.damage_box, def.hitbox);
+
+    let mob = Mob::new(def, vec2(100., 100.));
+    assert_eq!(mob.name(), "blob");
+    assert_eq!(mob.get_props().health, 30.);
+    // Sprite pixels are 6 world units wide
+    assert_eq!(mob.hitbox(), Rect::new(112., 124., 120., 120.));
+    assert!(mob.loot().is_none());
+}
+
+#[test]
+fn bad_definitions_say_what_is_wrong() {
+    let json = json!({ "texture": "slime", "health": 30 });
+    let err = MonsterDef::from_json("blob", &json).unwrap_err();
+    assert!(matches!(err, game::map::MapErrorKind::Field("hitbox")));
+}
+
+fn behaviour() -> Behaviour {
+    let json =
+        json!({ "leash_range": 1000, "attack_range": 100, "wind_up": 0.5, "flee_below": 0.3 });
+    Behaviour::from_json(&json, 500.)
+}
+
+#[test]
+fn brain_chases_winds_up_then_strikes() {
+    let behaviour = behaviour();
+    let mut brain = Brain::new(vec2(0., 0.));
+    let player = vec2(300., 0.);
+
+    assert_eq!(
+        brain.think(&behaviour, vec2(0., 0.), player, true, 1., 0.1),
+        Action::Stand
+    );
+    assert_eq!(brain.state, MobState::Chase);
+    assert_eq!(
+        brain.think(&behaviour, vec2(0., 0.), player, true, 1., 0.1),
+        Action::MoveTo(player, 1.)
+    );
+
+    // In range, but a bite from here would miss so it keeps closing in
+    let me = vec2(250., 0.);
+    assert_eq!(
+        brain.think(&behaviour, me, player, false, 1., 0.1),
+        Action::MoveTo(player, 1.)
+    );
+    assert_eq!(brain.state, MobState::Chase);
+
+    // Close enough to attack, but it has to wind up first
+    assert_eq!(
+        brain.think(&behaviour, me, player, true, 1., 0.1),
+        Action::Stand
+    );
+    assert!(brain.is_winding_up());
+    for _ in 0..5 {
+        assert_eq!(
+            brain.think(&behaviour, me, player, true, 1., 0.1),
+            Action::Stand
+        );
+    }
+    assert_eq!(
+        brain.think(&behaviour, me, player, true, 1., 0.1),
+        Action::Strike
+    );
+    assert!(matches!(brain.state, MobState::Cooldown(_)));
+}
+
+#[test]
+fn brain_gives_up_past_its_leash() {
+    let behaviour = behaviour();
+    let mut brain = Brain::new(vec2(0., 0.));
+    brain.state = MobState::Chase;
+
+    let me = vec2(1100., 0.);
+    let home = brain.home;
+    assert_eq!(
+        brain.think(&behaviour, me, vec2(1200., 0.), true, 1., 0.1),
+        Action::MoveTo(home, 1.)
+    );
+    assert_eq!(brain.state, MobState::Return);
+    // Still ignoring the player on the way back
+    assert_eq!(
+        brain.think(&behaviour, vec2(500., 0.), vec2(600., 0.), true, 1., 0.1),
+        Action::MoveTo(home, 1.)
+    );
+    assert_eq!(
+        brain.think(&behaviour, vec2(10., 0.), vec2(600., 0.), true, 1., 0.1),
+        Action::Stand
+    );
+    assert!(matches!(brain.state, MobState::Idle(_)));
+}
+
+#[test]
+fn brain_flees_when_hurt() {
+    let behaviour = behaviour();
+    let mut brain = Brain::new(vec2(0., 0.));
+    brain.state = MobState::Chase;
+
+    let player = vec2(200., 0.);
+    assert_eq!(
+        brain.think(&behaviour, vec2(0., 0.), player, true, 0.2, 0.1),
+        Action::MoveAway(player)
+    );
+    // Once the player is out of sight it heads home
+    let far_away = vec2(2000., 0.);
+    assert_eq!(
+        brain.think(&behaviour, vec2(0., 300.), far_away, true, 0.2, 0.1),
+        Action::MoveTo(brain.home, 1.)
+    );
+}
+
+#[test]
+fn stacked_monsters_spread_out() {
+    let mut area = common::open_map("Field", "");
+    let def = Rc::new(MonsterDef::from_json("blob", &blob_json()).unwrap());
+    let spot = vec2(10., 10.) * game::logic::TILE;
+    for _ in 0..4 {
+        area.enemies.push(Box::new(Mob::new(def.clone(), spot)));
+    }
+    // Someone standing right next to the pile
+    let npc_box = Rect::new(spot.x + 150., spot.y, 100., 100.);
+    let dialogue = game::dialogue::Dialogue::from_json(&json!({ "dialog": [""] })).unwrap();
+    area.npcs
+        .push(game::npc::NPC::new("John", dialogue, npc_box));
+
+    for _ in 0..120 {
+        area.separate_monsters();
+    }
+    // Rects that only touch still count as overlapping to macroquad
+    let overlap = |a: &Rect, b: &Rect| a.intersect(*b).map(|rect| rect.w.min(rect.h)).unwrap_or(0.);
+    let hitboxes: Vec<Rect> = area.enemies.iter().map(|mob| mob.hitbox()).collect();
+    for (i, a) in hitboxes.iter().enumerate() {
+        assert!(overlap(a, &npc_box) < 1., "{a:?} is inside the NPC");
+        for b in &hitboxes[i + 1..] {
+            assert!(overlap(a, b) < 1., "{a:?} and {b:?} still overlap");
+        }
+    }
+}
+
+#[test]
+fn spitter_shoots_the_player_from_afar() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let monsters = load_monsters().unwrap();
+    let mut area = common::open_map("Field", "");
+    let spot = vec2(5., 10.) * game::logic::TILE;
+    let mut spitter = Mob::new(monsters["spitter"].clone(), spot);
+    let mut player = game::player::Player::new("field".into());
+    player.props.pos = spot + vec2(500., 0.);
+    let health = player.props.health;
+
+    let dt = 1. / 60.;
+    for _ in 0..90 {
+        spitter.tick(
+            &mut player,
+            &area.walls,
+            &area.nav,
+            &mut area.projectiles,
+            dt,
+        );
+        player.invul_time.tick(dt);
+        let index = area.index_monsters();
+        let mut targets = game::map::Targets {
+            monsters: &mut area.enemies,
+            monster_index: &index,
+            player: &mut player,
+            walls: &area.walls,
+        };
+        for proj in area.projectiles.iter_mut() {
+            proj.tick(&mut targets, dt);
+        }
+    }
+    // One shot, and it never came close enough to bite
+    assert_eq!(player.props.health, health - 8.);
+}
+
+#[test]
+fn melee_monsters_hurt_a_player_standing_still() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let monsters = load_monsters().unwrap();
+    let area = common::open_map("Field", "");
+    let spot = vec2(10., 10.) * game::logic::TILE;
+    let sides = [
+        vec2(400., 0.),
+        vec2(-400., 0.),
+        vec2(0., 400.),
+        vec2(0., -400.),
+    ];
+
+    let dt = 1. / 60.;
+    for name in ["slime", "mushroom", "slime_king"] {
+        for side in sides {
+            let mut mob = Mob::new(monsters[name].clone(), spot + side);
+            let mut player = game::player::Player::new("field".into());
+            player.props.pos = spot;
+            let health = player.props.health;
+            for _ in 0..600 {
+                mob.tick(&mut player, &area.walls, &area.nav, &mut vec![], dt);
+                player.invul_time.tick(dt);
+            }
+            assert!(player.props.health < health, "{name} from {side} never hit");
+        }
+    }
+}
+
+#[test]
+fn boss_fight_seals_the_map_until_the_boss_dies() {
+    let layers = r#"
+        { "name": "Interactables", "type": "objectgroup", "objects": [
+            { "id": 5, "type": "chest", "x": 200, "y": 200,
+              "properties": [{ "name": "item", "type": "string", "value": "black_sword" }] }
+        ] },
+        { "name": "Bosses", "type": "objectgroup", "objects": [
+            { "id": 6, "type": "blob", "x": 16, "y": 16, "width": 160, "height": 160,
+              "properties": [
+                { "name": "reward", "type": "object", "value": 5 },
+                { "name": "flag", "type": "string", "value": "arena_cleared" }
+              ] }
+        ] }
+    "#;
+    let mut area = common::open_map("Arena", layers);
+    let mut json = blob_json();
+    json["phases"] = json!([{ "below": 0.5, "speed": 200 }]);
+    let def = Rc::new(MonsterDef::from_json("blob", &json).unwrap());
+    assert_eq!(def.phases[0].def.speed, 200.);
+    assert_eq!(def.phases[0].def.health, 30.);
+    let defs = std::collections::HashMap::from([("blob".into(), def)]);
+
+    let dt = 1. / 60.;
+    // Locked from the start, not just during the fight
+    assert!(!common::try_chest(&mut area));
+
+    let mut flags = game::flags::Flags::default();
+    let nowhere = Rect::new(-500., -500., 10., 10.);
+    area.tick_bosses(nowhere, &defs, &mut flags);
+    assert!(area.boss().is_none());
+    let inside = Rect::new(700., 700., 10., 10.);
+    area.tick_bosses(inside, &defs, &mut flags);
+    assert!(area.is_sealed());
+    assert_eq!(area.boss().unwrap().boss_title(), Some("blob"));
+
+    let mut player = game::player::Player::new("Arena".into());
+    player.props.pos = vec2(-5000., -5000.);
+    area.enemies[0].get_mut_props().health = 10.;
+    let mut projectiles = vec![];
+    area.enemies[0].tick(&mut player, &area.walls, &area.nav, &mut projectiles, dt);
+
+    area.enemies[0].get_mut_props().health = 0.;
+    for _ in 0..10 {
+        area.enemies[0].tick(&mut player, &area.walls, &area.nav, &mut projectiles, dt);
+    }
+    area.clean_up(&Default::default(), &Default::default());
+    assert!(!flags.is_set("arena_cleared"));
+    area.tick_bosses(inside, &defs, &mut flags);
+    assert!(!area.is_sealed());
+    assert!(common::try_chest(&mut area));
+    assert!(flags.is_set("arena_cleared"));
+}
+
+#[test]
+fn wave_spawner_sends_waves_until_cleared() {
+    let layers = r#"
+        { "name": "Interactables", "type": "objectgroup", "objects": [
+            { "id": 5, "type": "chest", "x": 200, "y": 200,
+              "properties": [{ "name": "item", "type": "string", "value": "black_sword" }] }
+        ] },
+        { "name": "Spawners", "type": "objectgroup", "objects": [
+            { "id": 7, "type": "", "x": 16, "y": 16, "width": 160, "height": 160,
+              "properties": [
+                { "name": "waves", "type": "string", "value": "blob 2; blob" },
+                { "name": "unlocks", "type": "object", "value": 5 }
+              ] }
+        ] }
+    "#;
+    let mut area = common::open_map("Arena", layers);
+    let def = Rc::new(MonsterDef::from_json("blob", &blob_json()).unwrap());
+    let defs = std::collections::HashMap::from([("blob".into(), def)]);
+
+    let dt = 1. / 60.;
+    let kill_all = |area: &mut game::map::Area| {
+        for monster in area.enemies.iter_mut() {
+            monster.get_mut_props().should_despawn = true;
+        }
+        area.clean_up(&Default::default(), &Default::default());
+    };
+
+    area.tick_spawners(Rect::new(-500., -500., 10., 10.), &defs, dt);
+    assert!(area.enemies.is_empty());
+    let inside = Rect::new(700., 700., 10., 10.);
+    area.tick_spawners(inside, &defs, dt);
+    assert_eq!(area.enemies.len(), 2);
+    // Nothing new while the first wave is still alive
+    area.tick_spawners(inside, &defs, dt);
+    assert_eq!(area.enemies.len(), 2);
+    assert!(!common::try_chest(&mut area));
+
+    kill_all(&mut area);
+    area.tick_spawners(inside, &defs, dt);
+    assert_eq!(area.enemies.len(), 1);
+    kill_all(&mut area);
+    area.tick_spawners(inside, &defs, dt);
+    assert!(area.enemies.is_empty());
+    assert!(common::try_chest(&mut area));
+
+    let err = game::monsters::spawner::parse_waves("slime three").unwrap_err();
+    assert!(matches!(err, game::map::MapErrorKind::BadWaves(_)));
+}
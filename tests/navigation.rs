@@ -0,0 +1,93 @@
+use game::logic::TILE;
+use game::navigation::NavGrid;
+use macroquad::prelude::*;
+
+// Builds a grid from a picture, # is a wall
+fn grid(rows: &[&str]) -> NavGrid {
+    let mut walls = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, cell) in row.chars().enumerate() {
+            if cell == '#' {
+                walls.push(Rect::new(x as f32 * TILE, y as f32 * TILE, TILE, TILE));
+            }
+        }
+    }
+    NavGrid::new(&walls, rows[0].len(), rows.len())
+}
+
+fn at(x: i32, y: i32) -> Vec2 {
+    NavGrid::center_of((x, y))
+}
+
+fn cells(path: &[Vec2]) -> Vec<(i32, i32)> {
+    path.iter().map(|point| NavGrid::cell_of(*point)).collect()
+}
+
+#[test]
+fn straight_path_in_open_room() {
+    #[rustfmt::skip]
+    let nav = grid(&[
+        "....",
+        "....",
+        "....",
+    ]);
+    assert!(nav.line_of_sight(at(0, 1), at(3, 1)));
+    let path = nav.find_path(at(0, 1), at(3, 1)).unwrap();
+    assert_eq!(cells(&path), [(1, 1), (2, 1), (3, 1)]);
+}
+
+#[test]
+fn goes_around_a_wall() {
+    #[rustfmt::skip]
+    let nav = grid(&[
+        ".....",
+        "..#..",
+        "..#..",
+        ".....",
+    ]);
+    assert!(!nav.line_of_sight(at(0, 1), at(4, 1)));
+    let path = nav.find_path(at(0, 1), at(4, 1)).unwrap();
+    assert_eq!(path.last().copied(), Some(at(4, 1)));
+    assert!(cells(&path).iter().all(|cell| !nav.is_blocked(*cell)));
+    // Over the top is shorter than under the bottom
+    assert!(cells(&path).contains(&(2, 0)));
+}
+
+#[test]
+fn does_not_cut_corners() {
+    #[rustfmt::skip]
+    let nav = grid(&[
+        "#.",
+        ".#",
+    ]);
+    // The only way from one open tile to the other is squeezing diagonally between the walls
+    assert!(nav.find_path(at(1, 0), at(0, 1)).is_none());
+}
+
+#[test]
+fn unreachable_goals_have_no_path() {
+    #[rustfmt::skip]
+    let nav = grid(&[
+        "..#..",
+        "..#..",
+        "..#..",
+    ]);
+    assert!(nav.find_path(at(0, 0), at(4, 2)).is_none());
+    // Walls themselves and places off the map can't be walked to either
+    assert!(nav.find_path(at(0, 0), at(2, 1)).is_none());
+    assert!(nav.find_path(at(0, 0), at(9, 9)).is_none());
+}
+
+#[test]
+fn finds_way_through_a_maze() {
+    #[rustfmt::skip]
+    let nav = grid(&[
+        ".#...",
+        ".#.#.",
+        ".#.#.",
+        "...#.",
+    ]);
+    let path = nav.find_path(at(0, 0), at(4, 3)).unwrap();
+    assert_eq!(path.len(), 13);
+    assert_eq!(path.last().copied(), Some(at(4, 3)));
+}
@@ -0,0 +1,75 @@
+use game::headless::Headless;
+use game::input::InputFrame;
+use game::map::*;
+use game::npc::Movement;
+use game::timestep::FIXED_DT;
+use macroquad::prelude::*;
+
+fn setup() -> Headless {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    Headless::new().unwrap()
+}
+
+#[test]
+fn open_paths_are_walked_back() {
+    let points = vec![vec2(0., 0.), vec2(1., 0.), vec2(2., 0.)];
+    let Movement::Patrol { points, .. } = Movement::patrol(points, false) else {
+        panic!("not a patrol")
+    };
+    assert_eq!(
+        points,
+        [vec2(0., 0.), vec2(1., 0.), vec2(2., 0.), vec2(1., 0.)]
+    );
+}
+
+#[test]
+fn villagers_stop_to_talk() {
+    let mut sim = setup();
+    sim.game.current_map = "Village".into();
+    let village = &sim.game.maps["Village"];
+    let index = village.npcs.iter().position(|npc| &*npc.name == "kid");
+    let index = index.unwrap();
+    let start = sim.game.maps["Village"].npcs[index].hitbox;
+    sim.run(&InputFrame::new(FIXED_DT), 60);
+    let walked = sim.game.maps["Village"].npcs[index].hitbox;
+    assert_ne!(start.point(), walked.point());
+
+    sim.game.maps.get_mut("Village").unwrap().npcs[index].is_talking = true;
+    sim.run(&InputFrame::new(FIXED_DT), 60);
+    let npc = &sim.game.maps["Village"].npcs[index];
+    assert_eq!(npc.hitbox.point(), walked.point());
+    assert!(!npc.moving);
+}
+
+#[test]
+fn paths_have_to_exist() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let map = r#"{
+        "class": "Lost",
+        "layers": [
+            { "name": "Terrain", "type": "tilelayer", "width": 2, "data": [1, 2, 3, 4] },
+            { "name": "NPCs", "type": "objectgroup", "objects": [
+                { "id": 3, "name": "kid", "x": 0, "y": 0, "properties": [
+                    { "name": "dialog", "type": "file", "value": "../dialogs/Kid.json" },
+                    { "name": "path", "type": "object", "value": 4 }
+                ] }
+            ] }
+        ]
+    }"#;
+    let errors = Area::from(map, "lost.json").err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].object, Some(3));
+    assert!(matches!(errors[0].kind, MapErrorKind::UnknownPath(4)));
+}
+
+#[test]
+fn sprites_default_to_the_name() {
+    let sim = setup();
+    let sprite = |map: &str, name: &str| {
+        let npcs = &sim.game.maps[map].npcs;
+        let npc = npcs.iter().find(|npc| &*npc.name == name).unwrap();
+        npc.sprite.clone()
+    };
+    assert_eq!(&*sprite("Village", "elder"), "adam");
+    assert_eq!(&*sprite("Passage", "alex"), "alex");
+}
@@ -0,0 +1,113 @@
+use game::flags::Action;
+use game::headless::Headless;
+use game::input::InputFrame;
+use game::logic::{GameState, Transition};
+use game::player::Collidable;
+use game::quests::*;
+use game::timestep::FIXED_DT;
+use game::ui::items::{Item, ItemEntity};
+use macroquad::prelude::*;
+use serde_json::json;
+
+fn setup() -> Headless {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    Headless::new().unwrap()
+}
+
+// Stands on the NPC and presses R
+fn talk_to(sim: &mut Headless, npc: &str) {
+    let map = &sim.game.maps[&sim.game.current_map];
+    let npc = map.npcs.iter().find(|other| &*other.name == npc).unwrap();
+    let center = npc.hitbox.center();
+    sim.game.player.props.pos += center - sim.game.player.pos();
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::R));
+    assert!(matches!(sim.game.state, GameState::Talking(..)));
+}
+
+#[test]
+fn objectives_need_a_goal() {
+    let quest = json!({
+        "name": "", "description": "",
+        "objectives": [{ "kill": "slime", "count": 5 }, { "talk": "fisherman" }],
+        "rewards": [{ "item": "mushroom", "count": 2 }]
+    });
+    let quest = QuestDef::from_json("test", &quest).unwrap();
+    assert_eq!(quest.objectives[0].goal, Goal::Kill("slime".into()));
+    assert_eq!(quest.objectives[1].count, 1);
+    assert_eq!(quest.rewards, vec![("mushroom".into(), 2)]);
+
+    let broken = json!({ "name": "", "description": "", "objectives": [{ "dance": "x" }] });
+    assert!(QuestDef::from_json("test", &broken).is_err());
+}
+
+#[test]
+fn the_elder_sends_you_to_the_lake() {
+    let mut sim = setup();
+    sim.game.current_map = "Village".into();
+    talk_to(&mut sim, "elder");
+    // Finish typing, then take the quest
+    let click = InputFrame::new(FIXED_DT).click(MouseButton::Left, vec2(-1e6, -1e6));
+    sim.step(click);
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Key1));
+    assert_eq!(sim.game.quests.state("visit_lake"), QuestState::Active);
+
+    sim.game.state = GameState::Transition(Transition::new(vec2(0., 0.), "Lake".into()));
+    sim.run(&InputFrame::new(FIXED_DT), 60);
+    assert_eq!(&*sim.game.current_map, "Lake");
+    assert_eq!(sim.game.quests.active[0].progress, vec![1, 0]);
+    talk_to(&mut sim, "fisherman");
+    assert_eq!(sim.game.quests.state("visit_lake"), QuestState::Ready);
+
+    sim.game
+        .run_action(&Action::FinishQuest("visit_lake".into()));
+    assert_eq!(sim.game.quests.state("visit_lake"), QuestState::Finished);
+    assert_eq!(sim.game.player.inventory.count("mushroom"), 3);
+}
+
+#[test]
+fn collected_items_are_handed_in() {
+    let mut sim = setup();
+    let defs = sim.game.item_defs.clone();
+    let slime = |count| Item::from_key(&defs, "slime", count).unwrap();
+    sim.game.player.inventory.append(slime(1));
+    sim.game.start_quest("slime_bait");
+    assert_eq!(sim.game.quests.active[0].progress, vec![0, 1]);
+
+    // Picked up off the ground
+    let pos = sim.game.player.props.pos;
+    let items = &mut sim.game.maps.get_mut(&sim.game.current_map).unwrap().items;
+    items.push(ItemEntity::new(slime(5), pos));
+    sim.step(InputFrame::new(FIXED_DT));
+    sim.game.quests.progress(&Goal::Kill("slime".into()), 3);
+    assert_eq!(sim.game.quests.state("slime_bait"), QuestState::Ready);
+
+    // Survives saving
+    let save = sim.game.to_save();
+    let mut other = setup();
+    other.game.apply_save(&save).unwrap();
+    assert_eq!(other.game.quests.active[0].progress, vec![3, 3]);
+
+    other.game.finish_quest("slime_bait");
+    assert!(other.game.quests.active.is_empty());
+    assert_eq!(other.game.player.inventory.count("slime"), 3);
+    assert_eq!(other.game.player.inventory.count("rusty_sword"), 1);
+}
+
+#[test]
+fn bought_items_count_too() {
+    let mut sim = setup();
+    let slime = sim.game.item_defs["slime"].clone();
+    sim.game.start_quest("slime_bait");
+    sim.game.quests.progress(&Goal::Kill("slime".into()), 3);
+    sim.game.player.coins = 3 * slime.value;
+    for _ in 0..3 {
+        assert!(sim.game.buy(&slime));
+    }
+    sim.step(InputFrame::new(FIXED_DT));
+    assert_eq!(sim.game.quests.state("slime_bait"), QuestState::Ready);
+
+    // Selling them again undoes it
+    assert!(sim.game.sell(0));
+    sim.step(InputFrame::new(FIXED_DT));
+    assert_eq!(sim.game.quests.state("slime_bait"), QuestState::Active);
+}
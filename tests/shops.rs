@@ -0,0 +1,85 @@
+use game::headless::Headless;
+use game::input::InputFrame;
+use game::logic::{GUIType, GameState};
+use game::player::Collidable;
+use game::timestep::FIXED_DT;
+use game::ui::items::{Item, ItemDef};
+use macroquad::prelude::*;
+use std::rc::Rc;
+
+fn setup() -> Headless {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    Headless::new().unwrap()
+}
+
+#[test]
+fn the_merchant_opens_a_shop() {
+    let mut sim = setup();
+    sim.game.current_map = "Village".into();
+    let village = &sim.game.maps["Village"];
+    let merchant = village.npcs.iter().find(|npc| &*npc.name == "merchant");
+    let center = merchant.unwrap().hitbox.center();
+    sim.game.player.props.pos += center - sim.game.player.pos();
+
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::R));
+    let click = InputFrame::new(FIXED_DT).click(MouseButton::Left, vec2(-1e6, -1e6));
+    sim.step(click.clone());
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Key1));
+    sim.step(click.clone());
+    sim.step(click);
+    let GameState::GUI(GUIType::Shop(shop)) = &sim.game.state else {
+        panic!("no shop after talking to the merchant")
+    };
+    assert_eq!(shop.stock.len(), 3);
+
+    sim.step(InputFrame::new(FIXED_DT).press(KeyCode::Escape));
+    assert!(matches!(sim.game.state, GameState::Normal));
+}
+
+#[test]
+fn buying_and_selling() {
+    let mut sim = setup();
+    let defs = sim.game.item_defs.clone();
+    let sword = defs["rusty_sword"].clone();
+
+    assert!(!sim.game.buy(&sword));
+    let slime = Item::from_key(&defs, "slime", 4).unwrap();
+    sim.game.player.inventory.append(slime);
+    assert!(sim.game.sell(0));
+    assert_eq!(sim.game.player.coins, 4 * defs["slime"].value);
+    assert!(sim.game.player.inventory.content[0].is_none());
+    // Nothing left to sell there
+    assert!(!sim.game.sell(0));
+
+    sim.game.player.coins = sword.value;
+    assert!(sim.game.buy(&sword));
+    assert_eq!(sim.game.player.coins, 0);
+    assert_eq!(sim.game.player.inventory.count("rusty_sword"), 1);
+
+    // Survives saving
+    sim.game.player.coins = 7;
+    let save = sim.game.to_save();
+    let mut other = setup();
+    other.game.apply_save(&save).unwrap();
+    assert_eq!(other.game.player.coins, 7);
+}
+
+#[test]
+fn free_items_and_huge_stacks() {
+    let mut sim = setup();
+    let json = serde_json::json!({
+        "name": "Đá", "description": "", "icon": [0, 0, 24, 24],
+        "value": 0, "class": "item", "max_stack": 99
+    });
+    let rock = Rc::new(ItemDef::from_json("rock", &json).unwrap());
+    assert!(!sim.game.buy(&rock));
+    assert_eq!(sim.game.player.inventory.count("rock"), 0);
+
+    let mut json = json;
+    json["value"] = u32::MAX.into();
+    let gem = Rc::new(ItemDef::from_json("gem", &json).unwrap());
+    sim.game.player.coins = 10;
+    sim.game.player.inventory.content[0] = Some(Item::new(gem, 99));
+    assert!(sim.game.sell(0));
+    assert_eq!(sim.game.player.coins, u32::MAX);
+}
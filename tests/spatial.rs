@@ -0,0 +1,46 @@
+use game::logic::TILE;
+use game::spatial::{SpatialGrid, Walls};
+use macroquad::prelude::*;
+
+#[test]
+fn grid_finds_everything_that_overlaps() {
+    let rects: Vec<Rect> = (0..40)
+        .map(|i| {
+            Rect::new(
+                (i % 8) as f32 * 3. * TILE,
+                (i / 8) as f32 * 2. * TILE,
+                TILE,
+                2. * TILE,
+            )
+        })
+        .collect();
+    let grid = SpatialGrid::from_rects(rects.iter().copied());
+
+    for probe in [
+        Rect::new(0., 0., 10., 10.),
+        Rect::new(5. * TILE, 3. * TILE, 7. * TILE, 4. * TILE),
+        Rect::new(-TILE, -TILE, 100. * TILE, 100. * TILE),
+    ] {
+        let expected: Vec<usize> = (0..rects.len())
+            .filter(|i| rects[*i].overlaps(&probe))
+            .collect();
+        let found: Vec<usize> = grid
+            .query(probe)
+            .into_iter()
+            .filter(|i| rects[*i].overlaps(&probe))
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn far_away_walls_are_skipped() {
+    let walls = Walls::new(vec![
+        Rect::new(0., 0., TILE, TILE),
+        Rect::new(40. * TILE, 40. * TILE, TILE, TILE),
+    ]);
+    let near: Vec<&Rect> = walls
+        .near(Rect::new(TILE / 2., TILE / 2., TILE, TILE))
+        .collect();
+    assert_eq!(near, [&walls.all()[0]]);
+}
@@ -0,0 +1,8 @@
+use game::validate::validate_maps;
+
+#[test]
+fn shipped_maps_are_valid() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let errors: Vec<String> = validate_maps().iter().map(|err| err.to_string()).collect();
+    assert!(errors.is_empty(), "{errors:#?}");
+}